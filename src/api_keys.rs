@@ -0,0 +1,188 @@
+use chrono::Utc;
+use clap::{Subcommand, ValueEnum};
+use rusqlite::{Connection, OptionalExtension, params};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+// What an API key may do; each scope includes the ones before it
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Scope {
+    /// GET /tokens
+    Read,
+    /// Also POST /ingest
+    Write,
+    /// Also GET /api-keys
+    Admin,
+}
+
+impl Scope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+// What a request may do: INGEST_TOKEN has every scope on all tenants, API keys the scope
+// and tenant they were created with
+pub struct Access {
+    pub scope: Scope,
+    pub tenant: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum TokenAuth {
+    /// Create an API key; it is printed once and only its hash is stored
+    Create {
+        name: String,
+        #[arg(long, value_enum, default_value_t = Scope::Read)]
+        scope: Scope,
+        /// Limit the key to the tokens of one tenant
+        #[arg(long, value_name = "TENANT")]
+        only_tenant: Option<String>,
+    },
+    /// List API keys with their scope and last use
+    List,
+    /// Revoke an API key
+    Revoke { name: String },
+}
+
+pub fn run(conn: &Connection, command: TokenAuth) -> Result<(), Box<dyn Error>> {
+    match command {
+        TokenAuth::Create {
+            name,
+            scope,
+            only_tenant,
+        } => {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM api_keys WHERE name = ?1)",
+                params![name],
+                |row| row.get(0),
+            )?;
+            if exists {
+                return Err(crate::exit::invalid(format!(
+                    "API key '{}' already exists",
+                    name
+                )));
+            }
+
+            let key = generate()?;
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            conn.execute(
+                "INSERT INTO api_keys (name, key_hash, scope, created_at, tenant)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![name, hash(&key), scope.as_str(), now, only_tenant],
+            )?;
+            eprintln!(
+                "API key '{}' created with {} scope on {}, store it now as it is not shown again:",
+                name,
+                scope.as_str(),
+                only_tenant
+                    .map(|tenant| format!("tenant '{}'", tenant))
+                    .unwrap_or_else(|| "all tenants".to_string())
+            );
+            println!("{}", key);
+        }
+        TokenAuth::List => {
+            println!(
+                "{:<20} {:<8} {:<16} {:<20} {}",
+                "Name", "Scope", "Tenant", "Created", "Last Used"
+            );
+            println!("{}", "-".repeat(87));
+            for key in list(conn)? {
+                println!(
+                    "{:<20} {:<8} {:<16} {:<20} {}",
+                    key.name,
+                    key.scope,
+                    key.tenant.as_deref().unwrap_or("(all)"),
+                    key.created_at,
+                    key.last_used_at.as_deref().unwrap_or("Never")
+                );
+            }
+        }
+        TokenAuth::Revoke { name } => {
+            if conn.execute("DELETE FROM api_keys WHERE name = ?1", params![name])? == 0 {
+                return Err(crate::exit::not_found(format!(
+                    "API key '{}' not found",
+                    name
+                )));
+            }
+            eprintln!("API key '{}' revoked", name);
+        }
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+pub struct ApiKey {
+    name: String,
+    scope: String,
+    tenant: Option<String>,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+pub fn list(conn: &Connection) -> rusqlite::Result<Vec<ApiKey>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, scope, tenant, created_at, last_used_at FROM api_keys ORDER BY name",
+    )?;
+    let keys = stmt
+        .query_map([], |row| {
+            Ok(ApiKey {
+                name: row.get(0)?,
+                scope: row.get(1)?,
+                tenant: row.get(2)?,
+                created_at: row.get(3)?,
+                last_used_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(keys)
+}
+
+pub fn any(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM api_keys)", [], |row| {
+        row.get(0)
+    })
+}
+
+// Access the key grants, recording its use. Keys are looked up by hash, so comparing
+// them leaks nothing about stored keys.
+pub fn authenticate(conn: &Connection, key: &str) -> rusqlite::Result<Option<Access>> {
+    let key_hash = hash(key);
+    let key: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT scope, tenant FROM api_keys WHERE key_hash = ?1",
+            params![key_hash],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((scope, tenant)) = key else {
+        return Ok(None);
+    };
+
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.execute(
+        "UPDATE api_keys SET last_used_at = ?1 WHERE key_hash = ?2",
+        params![now, key_hash],
+    )?;
+    Ok(Scope::from_str(&scope, true)
+        .ok()
+        .map(|scope| Access { scope, tenant }))
+}
+
+fn generate() -> Result<String, Box<dyn Error>> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate key: {}", e))?;
+    Ok(hex(&bytes))
+}
+
+fn hash(key: &str) -> String {
+    hex(&Sha256::digest(key.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
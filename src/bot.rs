@@ -0,0 +1,204 @@
+use rusqlite::{Connection, params};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+use crate::notify::{self, Channel};
+use crate::{Config, TOKEN_COLUMNS};
+
+// Long polling wait, below the HTTP client's 30 second timeout
+const POLL_TIMEOUT_SECONDS: u64 = 25;
+
+#[derive(Deserialize)]
+struct Updates {
+    result: Vec<Update>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+// Answers `/status <name>` on a background thread, using the bot of the first global
+// Telegram channel. Only chats that receive alerts get answers, and a tenant's chat
+// only sees that tenant's items.
+pub fn spawn(config: &Config) -> Result<(), Box<dyn Error>> {
+    let bot_token = config
+        .channels
+        .iter()
+        .find_map(|channel| match channel {
+            Channel::Telegram { bot_token, .. } => Some(bot_token.clone()),
+            _ => None,
+        })
+        .ok_or("TELEGRAM_BOT_COMMANDS needs a global Telegram channel")?;
+
+    // Chat ID to the tenant_env_key it is limited to, None for the global chats
+    let mut chats: HashMap<String, Option<String>> = HashMap::new();
+    for channel in &config.channels {
+        if let Channel::Telegram {
+            bot_token: bot,
+            chat_id,
+            ..
+        } = channel
+            && *bot == bot_token
+        {
+            chats.insert(chat_id.clone(), None);
+        }
+    }
+    for (key, channels) in &config.tenant_channels {
+        for channel in channels {
+            if let Channel::Telegram {
+                bot_token: bot,
+                chat_id,
+                ..
+            } = channel
+                && *bot == bot_token
+            {
+                chats
+                    .entry(chat_id.clone())
+                    .or_insert_with(|| Some(key.clone()));
+            }
+        }
+    }
+
+    let conn = crate::init_db(&config.db_path)?;
+    println!("Answering Telegram /status commands");
+    thread::spawn(move || {
+        let mut offset = 0;
+        loop {
+            match poll(&bot_token, offset) {
+                Ok(updates) => {
+                    for update in updates {
+                        offset = update.update_id + 1;
+                        if let Some(message) = update.message
+                            && let Err(e) = answer(&conn, &bot_token, &chats, message)
+                        {
+                            eprintln!("Failed to answer Telegram command: {}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to poll Telegram commands: {}", e);
+                    thread::sleep(Duration::from_secs(POLL_TIMEOUT_SECONDS));
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+fn poll(bot_token: &str, offset: i64) -> Result<Vec<Update>, Box<dyn Error>> {
+    let updates: Updates = crate::http::client()?
+        .get(format!(
+            "https://api.telegram.org/bot{}/getUpdates",
+            bot_token
+        ))
+        .query(&[
+            ("offset", offset.to_string()),
+            ("timeout", POLL_TIMEOUT_SECONDS.to_string()),
+            ("allowed_updates", r#"["message"]"#.to_string()),
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(updates.result)
+}
+
+fn answer(
+    conn: &Connection,
+    bot_token: &str,
+    chats: &HashMap<String, Option<String>>,
+    message: Message,
+) -> Result<(), Box<dyn Error>> {
+    let chat_id = message.chat.id.to_string();
+    // Chats that do not receive alerts get no answer
+    let Some(tenant_key) = chats.get(&chat_id) else {
+        return Ok(());
+    };
+    let Some(text) = message.text else {
+        return Ok(());
+    };
+
+    let (command, name) = text
+        .split_once(char::is_whitespace)
+        .unwrap_or((text.as_str(), ""));
+    // In groups commands may be addressed as /status@SomeBot
+    if command.split('@').next() != Some("/status") {
+        return Ok(());
+    }
+    let name = name.trim();
+    let reply = if name.is_empty() {
+        "Usage: /status <name>".to_string()
+    } else {
+        status(conn, name, tenant_key.as_deref())?
+    };
+    notify::send_telegram(bot_token, &chat_id, &reply)?;
+    Ok(())
+}
+
+fn status(
+    conn: &Connection,
+    name: &str,
+    tenant_key: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, owner, notes, renewal_url FROM tokens WHERE name = ?1 AND deleted_at IS NULL",
+        TOKEN_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map(params![name], |row| {
+            Ok((
+                crate::token_from_row(row)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+                row.get::<_, Option<String>>(8)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut replies = Vec::new();
+    for (token, owner, notes, renewal_url) in items {
+        if tenant_key.is_some_and(|key| notify::tenant_env_key(&token.tenant) != key) {
+            continue;
+        }
+        let mut reply = format!(
+            "📋 {} '{}'\nExpires: {} ({})",
+            token.kind.label(),
+            token.name,
+            token.expires_at,
+            crate::expiry_status(crate::days_until(&token.expires_at)?)
+        );
+        if token.tenant != crate::DEFAULT_TENANT {
+            reply.push_str(&format!("\nTenant: {}", token.tenant));
+        }
+        for (label, value) in [
+            ("Owner", owner),
+            ("Notes", notes),
+            ("Renew at", renewal_url),
+        ] {
+            if let Some(value) = value {
+                reply.push_str(&format!("\n{}: {}", label, value));
+            }
+        }
+        replies.push(reply);
+    }
+
+    if replies.is_empty() {
+        return Ok(format!("No item named '{}'", name));
+    }
+    Ok(replies.join("\n\n"))
+}
@@ -0,0 +1,46 @@
+use chrono::{Days, NaiveDate, Utc};
+use std::error::Error;
+
+// All-day iCalendar event on a token's expiry date. The UID only depends on the token
+// and date, so importing a newer invite for the same expiry updates the existing event.
+pub fn expiry_event(token: &str, expires_at: &str) -> Result<String, Box<dyn Error>> {
+    let date = NaiveDate::parse_from_str(expires_at, "%Y-%m-%d")?;
+    let end = date
+        .checked_add_days(Days::new(1))
+        .ok_or("Date out of range")?;
+
+    Ok([
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//expiration-notifier//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!(
+            "UID:{}-{}@expiration-notifier",
+            date.format("%Y%m%d"),
+            file_stem(token)
+        ),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+        format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+        format!("SUMMARY:{}", escape(&format!("Token '{}' expires", token))),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+        String::new(),
+    ]
+    .join("\r\n"))
+}
+
+// Token name usable in a file name or UID
+pub fn file_stem(token: &str) -> String {
+    token
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use crate::Config;
+
+// Seconds between the NTP epoch (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+// Fails when the system clock is further than CLOCK_MAX_SKEW_SECONDS from
+// CLOCK_CHECK_SOURCE, as every days-remaining value would be wrong. An unreachable
+// source only warns, so a network hiccup does not stop alerting.
+pub fn verify(config: &Config) -> Result<(), Box<dyn Error>> {
+    let Some(source) = &config.clock_check_source else {
+        return Ok(());
+    };
+    let reference = match reference_time(source) {
+        Ok(reference) => reference,
+        Err(e) => {
+            eprintln!(
+                "Warning: could not verify the clock against {}: {}",
+                source, e
+            );
+            return Ok(());
+        }
+    };
+
+    let skew = (Utc::now() - reference).num_seconds();
+    if skew.abs() > config.clock_max_skew_seconds {
+        return Err(format!(
+            "System clock is {}s {} {}, not evaluating expirations until it is fixed",
+            skew.abs(),
+            if skew > 0 { "ahead of" } else { "behind" },
+            source
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// ntp://<host>[:port] asks an NTP server, http(s):// URLs are read from the Date header
+fn reference_time(source: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    if let Some(host) = source.strip_prefix("ntp://") {
+        return ntp_time(host.trim_end_matches('/'));
+    }
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return http_time(source);
+    }
+    Err(format!(
+        "Unsupported CLOCK_CHECK_SOURCE '{}', use ntp:// or https://",
+        source
+    )
+    .into())
+}
+
+// Single SNTP request (RFC 4330)
+fn ntp_time(host: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:123", host)
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut packet = [0u8; 48];
+    packet[0] = 0x1B; // No leap warning, version 3, client mode
+    socket.send_to(&packet, &address)?;
+    let (length, _) = socket.recv_from(&mut packet)?;
+    if length < packet.len() {
+        return Err("Short NTP response".into());
+    }
+
+    // Transmit timestamp: seconds since 1900 and a 32-bit binary fraction
+    let seconds = i64::from(u32::from_be_bytes(packet[40..44].try_into()?));
+    let fraction = i64::from(u32::from_be_bytes(packet[44..48].try_into()?));
+    let nanos = (fraction * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(seconds - NTP_UNIX_OFFSET, nanos as u32)
+        .ok_or_else(|| "NTP time out of range".into())
+}
+
+#[cfg(feature = "http-client")]
+fn http_time(url: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let response = crate::http::client()?.head(url).send()?;
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .ok_or("Response has no Date header")?
+        .to_str()?;
+    Ok(DateTime::parse_from_rfc2822(date)?.with_timezone(&Utc))
+}
+
+#[cfg(not(feature = "http-client"))]
+fn http_time(_: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    Err("HTTP time sources need a build with an HTTP backend, use ntp:// instead".into())
+}
@@ -0,0 +1,73 @@
+use chrono::Local;
+use rusqlite::Connection;
+use std::error::Error;
+
+use crate::{Config, Token};
+
+// Row colors: expired, within the alert threshold
+const EXPIRED_COLOR: &str = "#f8d7da";
+const EXPIRING_COLOR: &str = "#fff3cd";
+
+// HTML page of the tenant's items expiring within the given number of days, soonest
+// first, for mailing to people who do not read chat alerts. There is no SMTP backend,
+// so the page is printed for sendmail or a notifier plugin to deliver.
+pub fn html(
+    conn: &Connection,
+    config: &Config,
+    tenant: &str,
+    within_days: i64,
+) -> Result<String, Box<dyn Error>> {
+    let mut upcoming: Vec<(Token, i64)> = Vec::new();
+    for token in crate::get_all_tokens(conn, Some(tenant))? {
+        let days_remaining = crate::days_until(&token.expires_at)?;
+        if days_remaining <= within_days {
+            upcoming.push((token, days_remaining));
+        }
+    }
+    upcoming.sort_by(|(a, _), (b, _)| a.expires_at.cmp(&b.expires_at));
+
+    let mut rows = String::new();
+    for (token, days_remaining) in &upcoming {
+        let color = if *days_remaining <= 0 {
+            EXPIRED_COLOR
+        } else if *days_remaining <= config.threshold_days(token.kind) {
+            EXPIRING_COLOR
+        } else {
+            "#ffffff"
+        };
+        rows.push_str(&format!(
+            "<tr style=\"background:{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            color,
+            escape(&token.name),
+            token.kind.as_str(),
+            token.expires_at,
+            crate::expiry_status(*days_remaining)
+        ));
+    }
+    if upcoming.is_empty() {
+        rows.push_str("<tr><td colspan=\"4\">Nothing expires in this period.</td></tr>\n");
+    }
+
+    Ok(format!(
+        "<!DOCTYPE html>
+<html>
+<body style=\"font-family:sans-serif\">
+<h2>Upcoming expirations</h2>
+<p>Items expiring within {} days, as of {}.</p>
+<table cellpadding=\"6\" style=\"border-collapse:collapse\" border=\"1\">
+<tr><th>Name</th><th>Kind</th><th>Expires</th><th>Status</th></tr>
+{}</table>
+</body>
+</html>",
+        within_days,
+        Local::now().format("%Y-%m-%d"),
+        rows
+    ))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
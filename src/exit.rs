@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Exit codes for scripts and cron jobs; 1 covers any other failure. Invalid command
+// lines rejected by clap exit with 2 as well.
+pub const INVALID: u8 = 2;
+pub const NOT_FOUND: u8 = 3;
+pub const STORAGE: u8 = 4;
+pub const NOTIFICATION: u8 = 5;
+
+// Error that exits with a specific code
+#[derive(Debug)]
+pub struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Failure {}
+
+pub fn invalid(message: impl Into<String>) -> Box<dyn Error> {
+    failure(INVALID, message)
+}
+
+pub fn not_found(message: impl Into<String>) -> Box<dyn Error> {
+    failure(NOT_FOUND, message)
+}
+
+pub fn notification(message: impl Into<String>) -> Box<dyn Error> {
+    failure(NOTIFICATION, message)
+}
+
+fn failure(code: u8, message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(Failure {
+        code,
+        message: message.into(),
+    })
+}
+
+// Database errors and unparseable dates are classified by type, so they need no wrapping
+pub fn code(error: &(dyn Error + 'static)) -> u8 {
+    if let Some(failure) = error.downcast_ref::<Failure>() {
+        return failure.code;
+    }
+    if let Some(error) = error.downcast_ref::<rusqlite::Error>() {
+        return match error {
+            rusqlite::Error::QueryReturnedNoRows => NOT_FOUND,
+            _ => STORAGE,
+        };
+    }
+    if error.is::<chrono::ParseError>() {
+        return INVALID;
+    }
+    match error.downcast_ref::<io::Error>() {
+        Some(error) if error.kind() == io::ErrorKind::NotFound => NOT_FOUND,
+        _ => 1,
+    }
+}
@@ -0,0 +1,80 @@
+use std::env;
+use std::io;
+use std::process::Command;
+
+use crate::Token;
+
+// External scripts run through `sh -c` with the token context in environment
+// variables. Hooks run synchronously, so long-running work should be backgrounded.
+// Their output goes to stderr, keeping stdout for the check report.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    on_check_start: Option<String>,
+    on_expiring: Option<String>,
+    on_expired: Option<String>,
+    on_renewed: Option<String>,
+}
+
+impl Hooks {
+    pub fn from_env() -> Self {
+        Self {
+            on_check_start: env::var("HOOK_ON_CHECK_START").ok(),
+            on_expiring: env::var("HOOK_ON_EXPIRING").ok(),
+            on_expired: env::var("HOOK_ON_EXPIRED").ok(),
+            on_renewed: env::var("HOOK_ON_RENEWED").ok(),
+        }
+    }
+
+    pub fn check_start(&self) {
+        run(&self.on_check_start, "check_start", &[]);
+    }
+
+    // Whether on_expiring or on_expired is set
+    pub fn on_expiry(&self) -> bool {
+        self.on_expiring.is_some() || self.on_expired.is_some()
+    }
+
+    // Runs on_expired once the expiry has passed, on_expiring before that
+    pub fn expiring(&self, token: &Token, days_remaining: i64) {
+        let vars = [
+            ("TOKEN_TENANT", token.tenant.clone()),
+            ("TOKEN_NAME", token.name.clone()),
+            ("TOKEN_EXPIRES_AT", token.expires_at.clone()),
+            ("TOKEN_DAYS_REMAINING", days_remaining.to_string()),
+        ];
+        if days_remaining <= 0 {
+            run(&self.on_expired, "expired", &vars);
+        } else {
+            run(&self.on_expiring, "expiring", &vars);
+        }
+    }
+
+    pub fn renewed(&self, tenant: &str, name: &str, previous_expires_at: &str, expires_at: &str) {
+        let vars = [
+            ("TOKEN_TENANT", tenant.to_string()),
+            ("TOKEN_NAME", name.to_string()),
+            ("TOKEN_EXPIRES_AT", expires_at.to_string()),
+            ("TOKEN_PREVIOUS_EXPIRES_AT", previous_expires_at.to_string()),
+        ];
+        run(&self.on_renewed, "renewed", &vars);
+    }
+}
+
+fn run(command: &Option<String>, event: &str, vars: &[(&str, String)]) {
+    let Some(command) = command else {
+        return;
+    };
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("HOOK_EVENT", event)
+        .envs(vars.iter().cloned())
+        .stdout(io::stderr())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Hook {} exited with {}", event, status),
+        Err(e) => eprintln!("Failed to run {} hook: {}", event, e),
+    }
+}
@@ -0,0 +1,74 @@
+use reqwest::blocking::Client;
+use reqwest::{Certificate, Identity, NoProxy, Proxy};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::sync::OnceLock;
+
+// Client for all outbound HTTP requests. HTTPS_PROXY, HTTP_PROXY and NO_PROXY are
+// honored by default; PROXY_URL sends every request through the given proxy instead,
+// still bypassing the hosts listed in NO_PROXY.
+//
+// Built once, so requests share its connection pool and the TLS files are read only
+// once; clones share the same client.
+pub fn client() -> Result<Client, Box<dyn Error>> {
+    static CLIENT: OnceLock<Result<Client, String>> = OnceLock::new();
+    match CLIENT.get_or_init(|| build().map_err(|e| e.to_string())) {
+        Ok(client) => Ok(client.clone()),
+        Err(e) => Err(e.clone().into()),
+    }
+}
+
+fn build() -> Result<Client, Box<dyn Error>> {
+    let mut builder = Client::builder();
+    if let Ok(proxy_url) = env::var("PROXY_URL") {
+        let proxy =
+            Proxy::all(proxy_url.as_str()).map_err(|e| format!("Invalid PROXY_URL: {}", e))?;
+        builder = builder.proxy(proxy.no_proxy(NoProxy::from_env()));
+    }
+
+    // Endpoints behind an internal PKI
+    if let Ok(path) = env::var("TLS_CA_BUNDLE") {
+        for certificate in read_pem_certificates(&path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    match (env::var("TLS_CLIENT_CERT"), env::var("TLS_CLIENT_KEY")) {
+        (Ok(cert_path), Ok(key_path)) => {
+            let identity = Identity::from_pkcs8_pem(&read(&cert_path)?, &read(&key_path)?)
+                .map_err(|e| format!("Invalid TLS client certificate: {}", e))?;
+            builder = builder.identity(identity);
+        }
+        (Err(_), Err(_)) => {}
+        _ => return Err("TLS_CLIENT_CERT and TLS_CLIENT_KEY must be set together".into()),
+    }
+
+    Ok(builder.build()?)
+}
+
+// Pings a dead man's switch such as a healthchecks.io check or an Uptime Kuma push monitor
+pub fn ping(url: &str) -> Result<(), Box<dyn Error>> {
+    client()?.get(url).send()?.error_for_status()?;
+    Ok(())
+}
+
+// A bundle may hold several PEM certificates
+fn read_pem_certificates(path: &str) -> Result<Vec<Certificate>, Box<dyn Error>> {
+    let pem = String::from_utf8(read(path)?)?;
+    let mut certificates = Vec::new();
+    for block in pem.split_inclusive("-----END CERTIFICATE-----") {
+        if block.contains("-----BEGIN CERTIFICATE-----") {
+            let certificate = Certificate::from_pem(block.trim().as_bytes())
+                .map_err(|e| format!("Invalid certificate in {}: {}", path, e))?;
+            certificates.push(certificate);
+        }
+    }
+    if certificates.is_empty() {
+        return Err(format!("No certificates found in {}", path).into());
+    }
+    Ok(certificates)
+}
+
+fn read(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?)
+}
@@ -0,0 +1,141 @@
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::exit;
+use crate::hooks::Hooks;
+
+// Token read from an import file
+#[derive(Deserialize)]
+struct Row {
+    name: String,
+    expires_at: String,
+}
+
+pub fn run(
+    conn: &Connection,
+    tenant: &str,
+    path: &Path,
+    hooks: &Hooks,
+) -> Result<(), Box<dyn Error>> {
+    let rows = read(path)?;
+
+    // One transaction for the whole file instead of an autocommit per row
+    let tx = conn.unchecked_transaction()?;
+    let mut renewals = Vec::new();
+    for row in &rows {
+        if let Some(previous) = crate::upsert_expiry(&tx, tenant, &row.name, &row.expires_at)? {
+            renewals.push((row, previous));
+        }
+    }
+    tx.commit()?;
+
+    for (row, previous) in renewals {
+        hooks.renewed(tenant, &row.name, &previous, &row.expires_at);
+    }
+    eprintln!("Imported {} token(s)", rows.len());
+
+    Ok(())
+}
+
+// Shows what run would change, without writing anything. Later rows for the same name
+// win, as they do on import.
+pub fn diff(conn: &Connection, tenant: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    let rows = read(path)?;
+
+    let mut imported: HashMap<&str, &str> = HashMap::new();
+    let (mut created, mut updated, mut unchanged) = (0, 0, 0);
+    for row in &rows {
+        let previous = match imported.get(row.name.as_str()) {
+            Some(expires_at) => Some(expires_at.to_string()),
+            None => crate::previous_expiry(conn, tenant, &row.name)?,
+        };
+        match previous {
+            None => {
+                println!("+ {:<30} expires_at: {}", row.name, row.expires_at);
+                created += 1;
+            }
+            Some(previous) if previous == row.expires_at => {
+                println!("= {:<30} unchanged", row.name);
+                unchanged += 1;
+            }
+            Some(previous) => {
+                println!(
+                    "~ {:<30} expires_at: {} -> {}",
+                    row.name, previous, row.expires_at
+                );
+                updated += 1;
+            }
+        }
+        imported.insert(&row.name, &row.expires_at);
+    }
+
+    println!();
+    println!(
+        "{} to create, {} to update, {} unchanged. Nothing was written.",
+        created, updated, unchanged
+    );
+    Ok(())
+}
+
+// .csv files hold a name,expires_at line per token, anything else a JSON array of
+// {"name", "expires_at"} objects
+fn read(path: &Path) -> Result<Vec<Row>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        let message = format!("Failed to read {}: {}", path.display(), e);
+        match e.kind() {
+            io::ErrorKind::NotFound => exit::not_found(message),
+            _ => message.into(),
+        }
+    })?;
+    let rows = if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+    {
+        parse_csv(&contents)?
+    } else {
+        serde_json::from_str(&contents)
+            .map_err(|e| exit::invalid(format!("Invalid JSON in {}: {}", path.display(), e)))?
+    };
+
+    for row in &rows {
+        NaiveDate::parse_from_str(&row.expires_at, "%Y-%m-%d")
+            .map_err(|e| exit::invalid(format!("Invalid expires_at for '{}': {}", row.name, e)))?;
+    }
+    Ok(rows)
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<Row>, Box<dyn Error>> {
+    let mut rows = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || (index == 0 && line.eq_ignore_ascii_case("name,expires_at"))
+        {
+            continue;
+        }
+
+        // Names may contain commas, dates never do
+        let (name, expires_at) = line.rsplit_once(',').ok_or_else(|| {
+            exit::invalid(format!("Line {}: expected name,expires_at", index + 1))
+        })?;
+        rows.push(Row {
+            name: unquote(name.trim()).to_string(),
+            expires_at: unquote(expires_at.trim()).to_string(),
+        });
+    }
+    Ok(rows)
+}
+
+fn unquote(field: &str) -> &str {
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::notify::{self, Alert};
+
+// Example URL shown for each channel type configured through NOTIFY_URLS
+const URL_EXAMPLES: &[(&str, &str)] = &[
+    ("ntfy", "ntfys://ntfy.sh/<topic>"),
+    ("gotify", "gotifys://gotify.example.com/<app_token>"),
+    ("slack", "slack://<token_a>/<token_b>/<token_c>"),
+    ("webhook", "jsons://example.com/<path>"),
+    ("email", "mailto://<recipient>?from=<sender>"),
+    ("url", "<scheme>://..."),
+];
+
+// Asks for channels, sends each a test message, asks for thresholds, then writes the
+// .env file of the profile and creates its database
+pub fn run(profile: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (env_file, db_path) = crate::profile_files(profile)?;
+    if Path::new(&env_file).exists()
+        && !confirm(
+            &format!("{} already exists, overwrite it?", env_file),
+            false,
+        )?
+    {
+        return Err("Setup cancelled, nothing was written".into());
+    }
+
+    let mut settings: Vec<(&str, String)> = Vec::new();
+    let mut channel_names = Vec::new();
+    let mut urls = Vec::new();
+    loop {
+        let kind = ask(
+            "Channel type (telegram, ntfy, gotify, slack, webhook, email or url)",
+            Some("telegram"),
+        )?;
+        if kind == "telegram" {
+            let bot_token = ask("Bot token from @BotFather", None)?;
+            let chat_id = ask("Chat ID", None)?;
+            if test_channel(&format!("tgram://{}/{}", bot_token, chat_id))? {
+                settings.push(("TELEGRAM_BOT_TOKEN", bot_token));
+                settings.push(("TELEGRAM_CHAT_ID", chat_id));
+                channel_names.push("telegram");
+            }
+        } else if let Some((_, example)) = URL_EXAMPLES.iter().find(|(name, _)| *name == kind) {
+            let url = ask(&format!("URL, e.g. {}", example), None)?;
+            if test_channel(&url)? {
+                urls.push(url);
+            }
+        } else {
+            eprintln!("Unknown channel type '{}'", kind);
+            continue;
+        }
+
+        if channel_names.is_empty() && urls.is_empty() {
+            eprintln!("At least one channel is needed");
+        } else if !confirm("Add another channel?", false)? {
+            break;
+        }
+    }
+
+    let threshold_days = ask_number("Days before expiry to start alerting", 1)?;
+    let check_interval = ask_number("Seconds between checks", 3600)?;
+
+    let mut contents = String::from("# Written by token-notifier init\n");
+    contents.push_str(&format!("NOTIFY_CHANNELS={}\n", channel_names.join(",")));
+    for (name, value) in &settings {
+        contents.push_str(&format!("{}={}\n", name, value));
+    }
+    if !urls.is_empty() {
+        contents.push_str(&format!("NOTIFY_URLS={}\n", urls.join(",")));
+    }
+    contents.push_str(&format!("NOTIFICATION_THRESHOLD_DAYS={}\n", threshold_days));
+    contents.push_str(&format!("CHECK_INTERVAL_SECONDS={}\n", check_interval));
+    write_private(&env_file, &contents)?;
+    crate::init_db(&db_path)?;
+
+    eprintln!("Wrote {} and created {}", env_file, db_path);
+    eprintln!("Next: track a token with `add <name> <YYYY-MM-DD>` and start `daemon`");
+    Ok(())
+}
+
+// Sends a test message, letting the user keep a channel that failed
+fn test_channel(url: &str) -> Result<bool, Box<dyn Error>> {
+    let result = notify::channel_from_url(url).and_then(|channel| {
+        notify::send(
+            &[channel],
+            &Alert::text("✅ expiration-notifier test message: this channel works".to_string()),
+        )
+    });
+    match result {
+        Ok(()) => {
+            eprintln!("Test message sent, check that it arrived");
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("Test failed: {}", e);
+            confirm("Keep this channel anyway?", false)
+        }
+    }
+}
+
+fn ask(question: &str, default: Option<&str>) -> Result<String, Box<dyn Error>> {
+    loop {
+        match default {
+            Some(default) => eprint!("{} [{}]: ", question, default),
+            None => eprint!("{}: ", question),
+        }
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Err("Setup cancelled, nothing was written".into());
+        }
+        match (answer.trim(), default) {
+            ("", Some(default)) => return Ok(default.to_string()),
+            ("", None) => continue,
+            (answer, _) => return Ok(answer.to_string()),
+        }
+    }
+}
+
+fn ask_number(question: &str, default: i64) -> Result<i64, Box<dyn Error>> {
+    loop {
+        match ask(question, Some(&default.to_string()))?.parse() {
+            Ok(number) if number >= 0 => return Ok(number),
+            _ => eprintln!("Please enter a whole number"),
+        }
+    }
+}
+
+fn confirm(question: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+    let answer = ask(
+        &format!("{} (y/n)", question),
+        Some(if default { "y" } else { "n" }),
+    )?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+// The file holds credentials, so only the owner may read it. An existing file is
+// restricted too, before the credentials are written to it.
+fn write_private(path: &str, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
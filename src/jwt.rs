@@ -0,0 +1,95 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::DateTime;
+use serde::Deserialize;
+use std::error::Error;
+
+// Registered claims used to track a JWT
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    exp: Option<f64>, // NumericDate, may be fractional
+    iss: Option<String>,
+    sub: Option<String>,
+}
+
+// Decodes the payload without verifying the signature: we only need the expiry
+pub fn decode(token: &str) -> Result<Claims, Box<dyn Error>> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").unwrap_or(token);
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or("Not a JWT: expected header.payload.signature")?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('='))?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+impl Claims {
+    pub fn expires_at(&self) -> Result<String, Box<dyn Error>> {
+        let exp = self.exp.ok_or("JWT has no exp claim")?;
+        let expires =
+            DateTime::from_timestamp(exp as i64, 0).ok_or("JWT exp claim is out of range")?;
+        Ok(expires.format("%Y-%m-%d").to_string())
+    }
+
+    // "issuer:subject", or whichever of the two is present
+    pub fn suggested_name(&self) -> Option<String> {
+        match (&self.iss, &self.sub) {
+            (Some(iss), Some(sub)) => Some(format!("{}:{}", iss, sub)),
+            (Some(claim), None) | (None, Some(claim)) => Some(claim.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Unsigned token with the given payload
+    fn token(payload: &str) -> String {
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#),
+            URL_SAFE_NO_PAD.encode(payload)
+        )
+    }
+
+    #[test]
+    fn decodes_registered_claims() {
+        let claims = decode(&token(
+            r#"{"exp":1893456000,"iss":"auth.example.com","sub":"ci-bot"}"#,
+        ))
+        .unwrap();
+        assert_eq!(claims.expires_at().unwrap(), "2030-01-01");
+        assert_eq!(
+            claims.suggested_name().as_deref(),
+            Some("auth.example.com:ci-bot")
+        );
+    }
+
+    #[test]
+    fn accepts_bearer_prefix_padding_and_fractional_exp() {
+        let padded =
+            token(r#"{"exp":1893456000.75,"sub":"ci-bot"}"#).replace(".signature", "==.signature");
+        let claims = decode(&format!("  Bearer {}\n", padded)).unwrap();
+        assert_eq!(claims.expires_at().unwrap(), "2030-01-01");
+        assert_eq!(claims.suggested_name().as_deref(), Some("ci-bot"));
+    }
+
+    #[test]
+    fn requires_exp_only_for_the_expiry() {
+        let claims = decode(&token("{}")).unwrap();
+        assert!(claims.expires_at().is_err());
+        assert_eq!(claims.suggested_name(), None);
+    }
+
+    #[test]
+    fn rejects_tokens_that_are_not_jwts() {
+        assert!(decode("").is_err());
+        assert!(decode("opaque-api-token").is_err());
+        assert!(decode("header.not*base64.signature").is_err());
+        assert!(decode(&token("not json")).is_err());
+        assert!(decode(&token(r#"{"exp":"tomorrow"}"#)).is_err());
+    }
+}
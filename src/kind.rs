@@ -0,0 +1,108 @@
+use clap::ValueEnum;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+
+// What a tracked item is. The kind sets how early alerts start, how they are worded
+// and how reports group items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Token,
+    Certificate,
+    Domain,
+    License,
+    Warranty,
+    Subscription,
+}
+
+impl Kind {
+    // As stored in the database and accepted by --kind
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Token => "token",
+            Kind::Certificate => "certificate",
+            Kind::Domain => "domain",
+            Kind::License => "license",
+            Kind::Warranty => "warranty",
+            Kind::Subscription => "subscription",
+        }
+    }
+
+    // Capitalized, for the start of alerts
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Token => "Token",
+            Kind::Certificate => "Certificate",
+            Kind::Domain => "Domain",
+            Kind::License => "License",
+            Kind::Warranty => "Warranty",
+            Kind::Subscription => "Subscription",
+        }
+    }
+
+    // Section heading in listings
+    pub fn plural(self) -> &'static str {
+        match self {
+            Kind::Token => "Tokens",
+            Kind::Certificate => "Certificates",
+            Kind::Domain => "Domains",
+            Kind::License => "Licenses",
+            Kind::Warranty => "Warranties",
+            Kind::Subscription => "Subscriptions",
+        }
+    }
+
+    // What happens at the expiry date, as (will ..., has ...)
+    pub fn verbs(self) -> (&'static str, &'static str) {
+        match self {
+            Kind::Warranty => ("end", "ended"),
+            Kind::Subscription => ("lapse", "lapsed"),
+            _ => ("expire", "expired"),
+        }
+    }
+
+    // Days before expiry that alerts start, unless <KIND>_THRESHOLD_DAYS is set.
+    // Tokens follow NOTIFICATION_THRESHOLD_DAYS.
+    fn default_threshold_days(self) -> Option<i64> {
+        match self {
+            Kind::Token => None,
+            Kind::Certificate => Some(14),
+            Kind::Domain | Kind::License | Kind::Warranty => Some(30),
+            Kind::Subscription => Some(7),
+        }
+    }
+}
+
+// Alert threshold of every kind
+pub fn thresholds_from_env(default_days: i64) -> Result<HashMap<Kind, i64>, Box<dyn Error>> {
+    let mut thresholds = HashMap::new();
+    for &kind in Kind::value_variants() {
+        let key = format!("{}_THRESHOLD_DAYS", kind.as_str().to_uppercase());
+        let days = match env::var(&key) {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| format!("{} must be a number", key))?,
+            Err(_) => kind.default_threshold_days().unwrap_or(default_days),
+        };
+        thresholds.insert(kind, days);
+    }
+    Ok(thresholds)
+}
+
+impl FromSql for Kind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let value = value.as_str()?;
+        Kind::from_str(value, true)
+            .map_err(|_| FromSqlError::Other(format!("Unknown kind '{}'", value).into()))
+    }
+}
+
+impl ToSql for Kind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
@@ -1,13 +1,70 @@
-use chrono::{Local, NaiveDate, Utc};
-use clap::{Parser, Subcommand};
-use reqwest::blocking::Client;
-use rusqlite::{Connection, Result as SqlResult, params};
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult, params};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
 use std::env;
 use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, IsTerminal, Write};
+use std::process::ExitCode;
+
+use hooks::Hooks;
+use kind::Kind;
+
+#[cfg(feature = "http-api")]
+mod api_keys;
+#[cfg(feature = "telegram")]
+mod bot;
+#[cfg(feature = "telegram")]
+mod calendar;
+mod clock;
+mod digest;
+mod exit;
+mod hooks;
+#[cfg(feature = "http-client")]
+mod http;
+mod import;
+mod init;
+mod jwt;
+mod kind;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod notify;
+mod owners;
+mod scan;
+mod secret;
+#[cfg(feature = "http-api")]
+mod server;
+mod stats;
+mod template;
+mod trash;
+
+// Stand-in when built without the mqtt feature; no publisher can exist
+#[cfg(not(feature = "mqtt"))]
+mod mqtt {
+    pub enum Publisher {}
+
+    impl Publisher {
+        pub fn publish_event(&self, _: &crate::Token, _: i64, _: &str) {
+            match *self {}
+        }
+
+        pub fn publish_status(
+            &self,
+            _: &[crate::Token],
+            _: &std::collections::HashMap<crate::Kind, i64>,
+        ) {
+            match *self {}
+        }
+    }
+}
 
 // Database setup
 const DB_NAME: &str = "token_notifier.db";
+const DEFAULT_TENANT: &str = "default";
 
 const CREATE_TABLE_SQL: &str = "
 CREATE TABLE IF NOT EXISTS tokens (
@@ -17,235 +74,2082 @@ CREATE TABLE IF NOT EXISTS tokens (
     last_notified TEXT
 )";
 
+// Schema changes applied in order, tracked with PRAGMA user_version
+const MIGRATIONS: &[&str] = &[
+    // JSON descriptor of the scan a token was imported from
+    "ALTER TABLE tokens ADD COLUMN source TEXT",
+    // Certificate identity, to spot unexpected replacements on re-scan
+    "ALTER TABLE tokens ADD COLUMN fingerprint TEXT;
+     ALTER TABLE tokens ADD COLUMN issuer TEXT",
+    // age-encrypted secret value
+    "ALTER TABLE tokens ADD COLUMN secret TEXT",
+    // Token names are unique per tenant, which needs a table rebuild in SQLite
+    "CREATE TABLE tokens_new (
+         id INTEGER PRIMARY KEY,
+         tenant TEXT NOT NULL DEFAULT 'default',
+         name TEXT NOT NULL,
+         expires_at TEXT NOT NULL,
+         last_notified TEXT,
+         source TEXT,
+         fingerprint TEXT,
+         issuer TEXT,
+         secret TEXT,
+         UNIQUE (tenant, name)
+     );
+     INSERT INTO tokens_new (id, name, expires_at, last_notified, source, fingerprint, issuer, secret)
+         SELECT id, name, expires_at, last_notified, source, fingerprint, issuer, secret
+         FROM tokens;
+     DROP TABLE tokens;
+     ALTER TABLE tokens_new RENAME TO tokens",
+    // Check cycles select by expiry
+    "CREATE INDEX idx_tokens_expires_at ON tokens (expires_at)",
+    // Last successful notification per channel, so failed channels are retried alone
+    "CREATE TABLE deliveries (
+         tenant TEXT NOT NULL,
+         name TEXT NOT NULL,
+         channel TEXT NOT NULL,
+         delivered_at TEXT NOT NULL,
+         PRIMARY KEY (tenant, name, channel)
+     )",
+    // Every expiry change, for rotation statistics
+    "CREATE TABLE history (
+         id INTEGER PRIMARY KEY,
+         tenant TEXT NOT NULL,
+         name TEXT NOT NULL,
+         old_expires_at TEXT NOT NULL,
+         new_expires_at TEXT NOT NULL,
+         changed_at TEXT NOT NULL
+     );
+     CREATE INDEX idx_history_token ON history (tenant, name)",
+    // Daemon state that has to survive restarts
+    "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    // Instructions appended to the token's alerts
+    "ALTER TABLE tokens ADD COLUMN message TEXT",
+    // Message to edit on the next alert, for channels that edit in place
+    "ALTER TABLE deliveries ADD COLUMN message_id INTEGER",
+    "ALTER TABLE tokens ADD COLUMN kind TEXT NOT NULL DEFAULT 'token'",
+    "ALTER TABLE tokens ADD COLUMN cost REAL;
+     ALTER TABLE tokens ADD COLUMN vendor TEXT",
+    // Hashed HTTP API keys managed with token-auth
+    "CREATE TABLE api_keys (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        key_hash TEXT NOT NULL UNIQUE,
+        scope TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        last_used_at TEXT
+    )",
+    // Who to ask about an item and how to renew it
+    "ALTER TABLE tokens ADD COLUMN owner TEXT;
+     ALTER TABLE tokens ADD COLUMN notes TEXT;
+     ALTER TABLE tokens ADD COLUMN renewal_url TEXT",
+    // Removed tokens stay in the trash until maintenance purges them
+    "ALTER TABLE tokens ADD COLUMN deleted_at TEXT",
+    // Name a scanner discovered a token under, when it was linked to a tracked token
+    "ALTER TABLE tokens ADD COLUMN scanned_as TEXT",
+    // Alerts claimed for sending, to drop duplicates within SUPPRESSION_WINDOW_SECONDS
+    "CREATE TABLE sent_alerts (fingerprint TEXT PRIMARY KEY, sent_at TEXT NOT NULL)",
+    // Tenant an API key is limited to, all tenants when NULL
+    "ALTER TABLE api_keys ADD COLUMN tenant TEXT",
+];
+
+// Expiries further away than 10 years are likely typos
+const MAX_PLAUSIBLE_DAYS: i64 = 3653;
+
+// Secrets that can also be read from the file named by <NAME>_FILE, including
+// tenant overrides such as TENANT_<NAME>_TELEGRAM_BOT_TOKEN_FILE
+const SECRET_VARS: &[&str] = &[
+    "TELEGRAM_BOT_TOKEN",
+    "NOTIFY_URLS",
+    "ESCALATION_URLS",
+    "INGEST_TOKEN",
+    "MQTT_PASSWORD",
+    "PROXY_URL",
+    "HEALTHCHECK_URL",
+    "HEALTHCHECK_FAIL_URL",
+    "AZURE_CLIENT_SECRET",
+    "GITHUB_TOKEN",
+    "GITLAB_TOKEN",
+    "KEYSTORE_PASSWORD",
+    "PROMETHEUS_TOKEN",
+];
+
+// Tokens still expired after this many days get escalated alerts
+const OVERDUE_ESCALATION_DAYS: i64 = 7;
+
+// Expiring tokens are read and notified this many at a time
+const NOTIFY_BATCH_SIZE: usize = 500;
+
+// Delivery name recording that today's on_expiring or on_expired hook ran
+const HOOK_DELIVERY: &str = "hook:expiring";
+
+// Longest single sleep between checks, so a check missed while the host was suspended
+// runs within this many seconds of it resuming
+const WAKE_CHECK_SECONDS: i64 = 60;
+
+const TOKEN_COLUMNS: &str = "tenant, name, expires_at, last_notified, message, kind";
+
 // Configuration
 #[derive(Debug)]
 struct Config {
-    telegram_bot_token: String,
-    telegram_chat_id: String,
-    notification_threshold_days: i64,
+    db_path: String,
+    channels: Vec<notify::Channel>,
+    // Keyed by tenant_env_key
+    tenant_channels: HashMap<String, Vec<notify::Channel>>,
+    escalation_channels: Vec<notify::Channel>,
+    owner_channels: HashMap<String, Vec<notify::Channel>>,
+    milestones: template::Milestones,
+    // Days before expiry that alerts start, for every kind
+    threshold_days: HashMap<Kind, i64>,
     check_interval_seconds: u64,
+    check_jitter_seconds: u64,
+    check_align: bool,
+    rescan_interval_seconds: u64,
+    notify_concurrency: usize,
+    suppression_window_seconds: i64,
+    heartbeat_interval_days: i64,
+    owner_summary_interval_days: i64,
+    owner_summary_within_days: i64,
+    notify_expiry_changes: bool,
+    history_retention_days: i64,
+    trash_retention_days: i64,
+    maintenance_interval_days: i64,
+    clock_check_source: Option<String>,
+    clock_max_skew_seconds: i64,
+    healthcheck_url: Option<String>,
+    healthcheck_fail_url: Option<String>,
+    http_listen_addr: Option<String>,
+    telegram_bot_commands: bool,
+    #[cfg(feature = "http-api")]
+    ingest_token: Option<String>,
+    mqtt_host: Option<String>,
+    #[cfg(feature = "mqtt")]
+    mqtt_port: u16,
+    #[cfg(feature = "mqtt")]
+    mqtt_username: Option<String>,
+    #[cfg(feature = "mqtt")]
+    mqtt_password: Option<String>,
+    #[cfg(feature = "mqtt")]
+    mqtt_topic_prefix: String,
+    hooks: hooks::Hooks,
 }
 
 // Token struct for database
 #[derive(Debug, Serialize, Deserialize)]
 struct Token {
+    tenant: String,
     name: String,
     expires_at: String, // ISO 8601 date string
     last_notified: Option<String>,
+    message: Option<String>,
+    kind: Kind,
 }
 
 // CLI Commands
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Use a separate database and .env.<PROFILE> configuration
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Tenant whose tokens to operate on
+    #[arg(long, global = true, default_value = DEFAULT_TENANT)]
+    tenant: String,
+    /// Reject commands that change tokens or reveal secrets, and serve the API read-only.
+    /// Checks still send alerts.
+    #[arg(long, global = true)]
+    read_only: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Set up channels and thresholds interactively, writing .env and the database
+    Init,
     /// Add a new token to track
-    Add { name: String, expires_at: String },
-    /// Remove a token from tracking
-    Remove { name: String },
+    Add {
+        name: String,
+        expires_at: String,
+        #[command(flatten)]
+        options: AddOptions,
+    },
+    /// Add a token using the expiry of a JWT (read from stdin when omitted)
+    AddJwt {
+        token: Option<String>,
+        /// Name to track the token under (defaults to the iss and sub claims)
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        options: AddOptions,
+    },
+    /// Move tokens to the trash, see `trash`
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Add or update tokens from a CSV (name,expires_at) or JSON file
+    Import {
+        file: std::path::PathBuf,
+        /// Only show which tokens would be created, updated or left unchanged
+        #[arg(long)]
+        diff: bool,
+    },
     /// List all tracked tokens
-    List,
+    List {
+        /// Keep the table on screen, redrawing it every --interval seconds
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between redraws in watch mode
+        #[arg(long, default_value_t = 60, requires = "watch")]
+        interval: u64,
+    },
+    /// Show the details of a token
+    Show {
+        name: String,
+        /// Decrypt and print the stored secret
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Change the details of a tracked token
+    Edit {
+        name: String,
+        #[command(flatten)]
+        options: EditOptions,
+    },
+    /// Store the secret value of a token encrypted, read from stdin
+    Secret {
+        name: String,
+        /// Remove the stored secret instead
+        #[arg(long)]
+        clear: bool,
+    },
     /// Start the notification daemon
     Daemon,
+    /// Check once, send due notifications and print a summary
+    Check {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Manage API keys for the HTTP API
+    #[cfg(feature = "http-api")]
+    TokenAuth {
+        #[command(subcommand)]
+        command: api_keys::TokenAuth,
+    },
+    /// Print an HTML digest of upcoming expirations, e.g. for sendmail
+    Digest {
+        /// How far ahead to look, in days (30 or 30d) or weeks (4w)
+        #[arg(long, default_value = "30d", value_parser = stats::parse_days)]
+        within: i64,
+    },
+    /// List or restore removed tokens
+    Trash {
+        #[command(subcommand)]
+        command: trash::Trash,
+    },
+    /// Prune old history and trash, then VACUUM and ANALYZE the database
+    Maintenance,
+    /// List notification channels, including plugins found on PATH
+    Channels,
+    /// Import tokens from an external source
+    Scan {
+        #[command(subcommand)]
+        source: scan::ScanSource,
+    },
+    /// Check alert templates or preview a token's alert
+    Template {
+        #[command(subcommand)]
+        command: template::Template,
+    },
+    /// Send each owner a summary of only their items that expire soon
+    OwnerSummary {
+        /// Print the summaries instead of sending them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Merge scanned tokens into tracked tokens for the same certificate or host
+    Dedupe {
+        /// Only show what would be merged
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report statistics from the expiry history and upcoming renewals
+    #[command(alias = "report")]
+    Stats {
+        #[command(subcommand)]
+        report: stats::Report,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    /// Machine-readable summary for orchestration systems
+    Json,
+    /// Nagios/Zabbix service check: status line with perfdata and exit code
+    Nagios,
+}
+
+// Adding a name that is already tracked fails unless one of these is given
+#[derive(Args)]
+struct AddOptions {
+    /// Replace an existing token, discarding its notification state and secret
+    #[arg(long, conflicts_with = "update")]
+    force: bool,
+    /// Change the expiry of an existing token, keeping everything else
+    #[arg(long)]
+    update: bool,
+    /// Allow an expiry date that has already passed
+    #[arg(long)]
+    allow_past: bool,
+    /// What is tracked; new items default to token
+    #[arg(long, value_enum)]
+    kind: Option<Kind>,
+    /// Renewal cost, summed by `report renewals --sum-cost`
+    #[arg(long)]
+    cost: Option<f64>,
+    /// Who the item is renewed with
+    #[arg(long)]
+    vendor: Option<String>,
+}
+
+#[derive(Args)]
+struct EditOptions {
+    /// Text appended to the token's alerts; {name}, {kind}, {expires_at} and {days} are replaced
+    #[arg(long)]
+    message: Option<String>,
+    /// Remove the custom alert text
+    #[arg(long, conflicts_with = "message")]
+    clear_message: bool,
+    /// Change what is tracked
+    #[arg(long, value_enum)]
+    kind: Option<Kind>,
+    /// Renewal cost
+    #[arg(long)]
+    cost: Option<f64>,
+    /// Remove the renewal cost
+    #[arg(long, conflicts_with = "cost")]
+    clear_cost: bool,
+    /// Who the item is renewed with
+    #[arg(long)]
+    vendor: Option<String>,
+    /// Remove the vendor
+    #[arg(long, conflicts_with = "vendor")]
+    clear_vendor: bool,
+    /// Person or team responsible for renewing the item
+    #[arg(long)]
+    owner: Option<String>,
+    /// Remove the owner
+    #[arg(long, conflicts_with = "owner")]
+    clear_owner: bool,
+    /// Free-form notes, shown by `show` and the Telegram /status command
+    #[arg(long)]
+    notes: Option<String>,
+    /// Remove the notes
+    #[arg(long, conflicts_with = "notes")]
+    clear_notes: bool,
+    /// Where the item is renewed
+    #[arg(long)]
+    renewal_url: Option<String>,
+    /// Remove the renewal URL
+    #[arg(long, conflicts_with = "renewal_url")]
+    clear_renewal_url: bool,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+impl Commands {
+    // Commands safe to expose to people who may only look at the inventory. Checks only
+    // record which alerts went out, never the tokens themselves.
+    fn allowed_read_only(&self) -> bool {
+        match self {
+            Commands::List { .. }
+            | Commands::Channels
+            | Commands::Check { .. }
+            | Commands::Daemon
+            | Commands::Stats { .. }
+            | Commands::Digest { .. }
+            | Commands::Template { .. } => true,
+            Commands::Show { reveal, .. } => !reveal,
+            Commands::Import { diff, .. } => *diff,
+            Commands::Dedupe { dry_run } | Commands::OwnerSummary { dry_run } => *dry_run,
+            Commands::Trash { command } => matches!(command, trash::Trash::List),
+            _ => false,
+        }
+    }
+}
+
+// Messages for people go to stderr and data to stdout, and failures exit with the codes
+// in the exit module
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    // Monitoring systems need UNKNOWN rather than a plain failure, including when the
+    // configuration or database is broken
+    let nagios = matches!(
+        cli.command,
+        Commands::Check {
+            format: OutputFormat::Nagios
+        }
+    );
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if nagios => {
+            println!("UNKNOWN - {}", e);
+            ExitCode::from(3)
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(exit::code(e.as_ref()))
+        }
+    }
+}
 
-    // Initialize database
-    let conn = init_db()?;
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    if cli.read_only && !cli.command.allowed_read_only() {
+        return Err(exit::invalid(
+            "This command is not allowed in read-only mode",
+        ));
+    }
+
+    // Runs before the configuration it writes is loaded
+    if let Commands::Init = cli.command {
+        return init::run(cli.profile.as_deref());
+    }
 
     // Load configuration
-    let config = Config::from_env()?;
+    let config =
+        Config::from_env(cli.profile.as_deref()).map_err(|e| exit::invalid(e.to_string()))?;
 
+    // Initialize database
+    let conn = init_db(&config.db_path)?;
+    if config.notify_expiry_changes {
+        track_expiry_changes(&conn)?;
+    }
+
+    let changes_expiry = matches!(
+        cli.command,
+        Commands::Add { .. }
+            | Commands::AddJwt { .. }
+            | Commands::Import { diff: false, .. }
+            | Commands::Scan { .. }
+    );
+    let tenant = cli.tenant.as_str();
     match cli.command {
-        Commands::Add { name, expires_at } => {
-            add_token(&conn, &name, &expires_at)?;
-            println!("Token '{}' added successfully!", name);
+        Commands::Init => unreachable!("handled before loading the configuration"),
+        Commands::Add {
+            name,
+            expires_at,
+            options,
+        } => {
+            add_token(&conn, tenant, &name, &expires_at, &options, &config.hooks)?;
+            eprintln!("Token '{}' added successfully!", name);
+        }
+        Commands::AddJwt {
+            token,
+            name,
+            options,
+        } => {
+            let token = match token {
+                Some(token) if token != "-" => token,
+                _ => io::read_to_string(io::stdin())?,
+            };
+            let claims = jwt::decode(&token).map_err(|e| exit::invalid(e.to_string()))?;
+            let expires_at = claims
+                .expires_at()
+                .map_err(|e| exit::invalid(e.to_string()))?;
+            let name = name
+                .or_else(|| claims.suggested_name())
+                .ok_or_else(|| exit::invalid("JWT has no iss or sub claim, pass --name"))?;
+
+            add_token(&conn, tenant, &name, &expires_at, &options, &config.hooks)?;
+            eprintln!(
+                "Token '{}' added successfully! Expires {}",
+                name, expires_at
+            );
+        }
+        Commands::Remove { names } => {
+            remove_tokens(&conn, tenant, &names)?;
+            for name in names {
+                eprintln!("Token '{}' moved to the trash", name);
+            }
         }
-        Commands::Remove { name } => {
-            remove_token(&conn, &name)?;
-            println!("Token '{}' removed successfully!", name);
+        Commands::Import { file, diff: true } => {
+            import::diff(&conn, tenant, &file)?;
         }
-        Commands::List => {
-            list_tokens(&conn)?;
+        Commands::Import { file, diff: false } => {
+            import::run(&conn, tenant, &file, &config.hooks)?;
+        }
+        Commands::List { watch: false, .. } => {
+            list_tokens(&conn, tenant)?;
+        }
+        Commands::List {
+            watch: true,
+            interval,
+        } => {
+            watch_tokens(&conn, tenant, interval)?;
+        }
+        Commands::Show { name, reveal } => {
+            show_token(&conn, tenant, &name, reveal)?;
+        }
+        Commands::Edit { name, options } => {
+            edit_token(&conn, tenant, &name, &options)?;
+            eprintln!("Token '{}' updated successfully!", name);
+        }
+        Commands::Secret { name, clear } => {
+            if clear {
+                set_secret(&conn, tenant, &name, None)?;
+                eprintln!("Secret of token '{}' removed!", name);
+            } else {
+                if io::stdin().is_terminal() {
+                    eprint!("Secret for '{}': ", name);
+                }
+                let value = io::read_to_string(io::stdin())?;
+                let encrypted = secret::encrypt(value.trim_end_matches(['\r', '\n']))?;
+                set_secret(&conn, tenant, &name, Some(&encrypted))?;
+                eprintln!("Secret of token '{}' stored encrypted!", name);
+            }
         }
         Commands::Daemon => {
-            run_daemon(&conn, &config)?;
+            run_daemon(&conn, &config, cli.read_only)?;
+        }
+        // Failures are reported as UNKNOWN by main
+        Commands::Check {
+            format: OutputFormat::Nagios,
+        } => {
+            let (status, code) = nagios_status(&check_and_notify(&conn, &config, None)?);
+            println!("{}", status);
+            std::process::exit(code);
+        }
+        Commands::Check { format } => {
+            let report = check_and_notify(&conn, &config, None)?;
+            print_check_report(&report, format)?;
+            let failed = report
+                .items
+                .iter()
+                .flat_map(|item| &item.notifications)
+                .filter(|notification| !notification.sent)
+                .count();
+            if failed > 0 {
+                return Err(exit::notification(format!(
+                    "{} notification(s) failed",
+                    failed
+                )));
+            }
+        }
+        #[cfg(feature = "http-api")]
+        Commands::TokenAuth { command } => {
+            api_keys::run(&conn, command)?;
+        }
+        Commands::Digest { within } => {
+            println!("{}", digest::html(&conn, &config, tenant, within)?);
+        }
+        Commands::Trash { command } => {
+            trash::run(&conn, tenant, command, config.trash_retention_days)?;
+        }
+        Commands::Maintenance => {
+            let (history, trashed, deliveries) = run_maintenance(&conn, &config)?;
+            eprintln!(
+                "Pruned {} history entries, {} trashed tokens and {} stale deliveries, database compacted",
+                history, trashed, deliveries
+            );
+        }
+        Commands::Channels => {
+            list_channels(&config);
+        }
+        Commands::Scan { source } => {
+            scan::run(&conn, tenant, source, &config.hooks)?;
+        }
+        Commands::Template { command } => {
+            template::run(&conn, &config, tenant, command)?;
+        }
+        Commands::OwnerSummary { dry_run } => {
+            let sent = owners::send(&conn, &config, dry_run)?;
+            eprintln!("{} owner summary(s)", sent);
+        }
+        Commands::Dedupe { dry_run } => {
+            scan::dedupe(&conn, tenant, dry_run)?;
+        }
+        Commands::Stats { report } => {
+            stats::run(&conn, tenant, report)?;
         }
     }
 
+    if changes_expiry && let Err(e) = notify_expiry_changes(&conn, &config) {
+        eprintln!("Error notifying expiry changes: {}", e);
+    }
+
     Ok(())
 }
 
 // Database functions
-fn init_db() -> SqlResult<Connection> {
-    let conn = Connection::open(DB_NAME)?;
+fn init_db(path: &str) -> SqlResult<Connection> {
+    let conn = Connection::open(path)?;
+    // The daemon's HTTP server writes through a second connection
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
     conn.execute(CREATE_TABLE_SQL, [])?;
+    migrate(&conn)?;
     Ok(conn)
 }
 
-fn add_token(conn: &Connection, name: &str, expires_at: &str) -> SqlResult<()> {
-    // Validate date format
-    NaiveDate::parse_from_str(expires_at, "%Y-%m-%d")
-        .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+fn migrate(conn: &Connection) -> SqlResult<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        conn.execute_batch(&format!(
+            "BEGIN; {}; PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        ))?;
+    }
+    Ok(())
+}
 
-    conn.execute(
-        "INSERT OR REPLACE INTO tokens (name, expires_at) VALUES (?1, ?2)",
-        params![name, expires_at],
-    )?;
+fn add_token(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    expires_at: &str,
+    options: &AddOptions,
+    hooks: &Hooks,
+) -> Result<(), Box<dyn Error>> {
+    // A typo'd year would otherwise sit in the database firing alerts
+    let days_remaining = days_until(expires_at)?;
+    if days_remaining < 0 && !options.allow_past {
+        return Err(exit::invalid(format!(
+            "Expiry {} is in the past, pass --allow-past to add it anyway",
+            expires_at
+        )));
+    }
+    if days_remaining > MAX_PLAUSIBLE_DAYS {
+        eprintln!(
+            "Warning: expiry {} is more than 10 years away, check the year",
+            expires_at
+        );
+    }
+
+    let renewed_from = if options.update {
+        upsert_expiry(conn, tenant, name, expires_at)?
+    } else {
+        let previous = previous_expiry(conn, tenant, name)?;
+        if previous.is_some() && !options.force {
+            return Err(exit::invalid(format!(
+                "Token '{}' already exists, use --update to change its expiry or --force to replace it",
+                name
+            )));
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO tokens (tenant, name, expires_at) VALUES (?1, ?2, ?3)",
+            params![tenant, name, expires_at],
+        )?;
+        conn.execute(
+            "DELETE FROM deliveries WHERE tenant = ?1 AND name = ?2",
+            params![tenant, name],
+        )?;
+        if let Some(previous) = &previous {
+            record_expiry_change(conn, tenant, name, previous, expires_at)?;
+        }
+        previous.filter(|previous| previous.as_str() < expires_at)
+    };
+    if options.kind.is_some() || options.cost.is_some() || options.vendor.is_some() {
+        conn.execute(
+            "UPDATE tokens SET kind = COALESCE(?1, kind), cost = COALESCE(?2, cost),
+                 vendor = COALESCE(?3, vendor)
+             WHERE tenant = ?4 AND name = ?5",
+            params![options.kind, options.cost, options.vendor, tenant, name],
+        )?;
+    }
+    if let Some(previous) = renewed_from {
+        hooks.renewed(tenant, name, &previous, expires_at);
+    }
     Ok(())
 }
 
-fn remove_token(conn: &Connection, name: &str) -> SqlResult<()> {
-    conn.execute("DELETE FROM tokens WHERE name = ?1", params![name])?;
+// Statements used per row of bulk operations are cached so they are prepared once
+fn previous_expiry(conn: &Connection, tenant: &str, name: &str) -> SqlResult<Option<String>> {
+    conn.prepare_cached(
+        "SELECT expires_at FROM tokens WHERE tenant = ?1 AND name = ?2 AND deleted_at IS NULL",
+    )?
+    .query_row(params![tenant, name], |row| row.get(0))
+    .optional()
+}
+
+// Creates the token or updates its expiry, keeping its state. Returns the previous
+// expiry when it moved forward.
+fn upsert_expiry(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    expires_at: &str,
+) -> SqlResult<Option<String>> {
+    let previous = previous_expiry(conn, tenant, name)?;
+    // Adding a trashed token again starts over rather than reviving the trashed copy
+    conn.prepare_cached(
+        "DELETE FROM tokens WHERE tenant = ?1 AND name = ?2 AND deleted_at IS NOT NULL",
+    )?
+    .execute(params![tenant, name])?;
+    conn.prepare_cached(
+        "INSERT INTO tokens (tenant, name, expires_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(tenant, name) DO UPDATE SET expires_at = excluded.expires_at",
+    )?
+    .execute(params![tenant, name, expires_at])?;
+    if let Some(previous) = &previous {
+        record_expiry_change(conn, tenant, name, previous, expires_at)?;
+    }
+    Ok(previous.filter(|previous| previous.as_str() < expires_at))
+}
+
+// Appends to the history when a known token's expiry changed
+fn record_expiry_change(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    old_expires_at: &str,
+    new_expires_at: &str,
+) -> SqlResult<()> {
+    if old_expires_at == new_expires_at {
+        return Ok(());
+    }
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.prepare_cached(
+        "INSERT INTO history (tenant, name, old_expires_at, new_expires_at, changed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![tenant, name, old_expires_at, new_expires_at, now])?;
     Ok(())
 }
 
-fn list_tokens(conn: &Connection) -> SqlResult<()> {
-    let mut stmt = conn.prepare("SELECT name, expires_at, last_notified FROM tokens")?;
-    let token_iter = stmt.query_map([], |row| {
-        Ok(Token {
-            name: row.get(0)?,
-            expires_at: row.get(1)?,
-            last_notified: row.get(2)?,
-        })
-    })?;
+// Removed tokens keep their data in the trash, see run_maintenance
+fn remove_tokens(conn: &Connection, tenant: &str, names: &[String]) -> Result<(), Box<dyn Error>> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "UPDATE tokens SET deleted_at = ?1
+             WHERE tenant = ?2 AND name = ?3 AND deleted_at IS NULL",
+        )?;
+        let mut deliveries =
+            tx.prepare_cached("DELETE FROM deliveries WHERE tenant = ?1 AND name = ?2")?;
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        for name in names {
+            // Nothing is removed unless every token exists
+            if stmt.execute(params![now, tenant, name])? == 0 {
+                return Err(exit::not_found(format!("Token '{}' not found", name)));
+            }
+            deliveries.execute(params![tenant, name])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
 
-    println!("Tracked Tokens:");
-    println!("{:<20} {:<15} {}", "Name", "Expires", "Last Notified");
-    println!("{}", "-".repeat(50));
+// Maps a row selected with TOKEN_COLUMNS
+fn token_from_row(row: &rusqlite::Row) -> SqlResult<Token> {
+    Ok(Token {
+        tenant: row.get(0)?,
+        name: row.get(1)?,
+        expires_at: row.get(2)?,
+        last_notified: row.get(3)?,
+        message: row.get(4)?,
+        kind: row.get(5)?,
+    })
+}
+
+// Tokens of one tenant, or of all tenants
+fn get_all_tokens(conn: &Connection, tenant: Option<&str>) -> SqlResult<Vec<Token>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM tokens WHERE (?1 IS NULL OR tenant = ?1) AND deleted_at IS NULL",
+        TOKEN_COLUMNS
+    ))?;
+    let tokens = stmt
+        .query_map(params![tenant], token_from_row)?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    Ok(tokens)
+}
+
+// One table per kind of tracked item
+fn list_tokens(conn: &Connection, tenant: &str) -> Result<(), Box<dyn Error>> {
+    let tokens = get_all_tokens(conn, Some(tenant))?;
+    let mut first = true;
+    for &kind in <Kind as clap::ValueEnum>::value_variants() {
+        let group: Vec<_> = tokens.iter().filter(|token| token.kind == kind).collect();
+        // An empty inventory still prints the (empty) tokens table
+        if group.is_empty() && !(tokens.is_empty() && kind == Kind::Token) {
+            continue;
+        }
+        if !first {
+            println!();
+        }
+        first = false;
 
-    for token in token_iter {
-        let token = token?;
+        println!("Tracked {}:", kind.plural());
         println!(
-            "{:<20} {:<15} {}",
-            token.name,
-            token.expires_at,
-            token.last_notified.unwrap_or_else(|| "Never".to_string())
+            "{:<20} {:<15} {:<22} {}",
+            "Name", "Expires", "Status", "Last Notified"
         );
+        println!("{}", "-".repeat(75));
+        for token in group {
+            println!(
+                "{:<20} {:<15} {:<22} {}",
+                token.name,
+                token.expires_at,
+                expiry_status(days_until(&token.expires_at)?),
+                token.last_notified.as_deref().unwrap_or("Never")
+            );
+        }
     }
 
     Ok(())
 }
 
-fn get_expiring_tokens(conn: &Connection, threshold_days: i64) -> SqlResult<Vec<Token>> {
-    let now = Utc::now().format("%Y-%m-%d").to_string();
-    let mut stmt = conn.prepare(
-        "SELECT name, expires_at, last_notified FROM tokens 
-         WHERE date(expires_at) <= date(?1, '+' || ?2 || ' days')",
+// Redraw the token list until interrupted, e.g. for a wall monitor
+fn watch_tokens(conn: &Connection, tenant: &str, interval: u64) -> Result<(), Box<dyn Error>> {
+    if interval == 0 {
+        return Err(exit::invalid("--interval must be greater than 0"));
+    }
+    loop {
+        // Clear the screen and move the cursor home before each redraw
+        print!("\x1B[2J\x1B[H");
+        println!(
+            "Every {}s, updated {}\n",
+            interval,
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        list_tokens(conn, tenant)?;
+        io::stdout().flush()?;
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+    }
+}
+
+// "in 3 days", "expires today" or "expired 12 days ago"
+fn expiry_status(days_remaining: i64) -> String {
+    match days_remaining {
+        0 => "expires today".to_string(),
+        1 => "in 1 day".to_string(),
+        -1 => "expired 1 day ago".to_string(),
+        days if days > 0 => format!("in {} days", days),
+        days => format!("expired {} days ago", -days),
+    }
+}
+
+fn show_token(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    reveal: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (token, source, encrypted, cost, vendor, owner, notes, renewal_url) = conn
+        .query_row(
+            &format!(
+                "SELECT {}, source, secret, cost, vendor, owner, notes, renewal_url FROM tokens
+                 WHERE tenant = ?1 AND name = ?2 AND deleted_at IS NULL",
+                TOKEN_COLUMNS
+            ),
+            params![tenant, name],
+            |row| {
+                Ok((
+                    token_from_row(row)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<f64>>(8)?,
+                    row.get::<_, Option<String>>(9)?,
+                    row.get::<_, Option<String>>(10)?,
+                    row.get::<_, Option<String>>(11)?,
+                    row.get::<_, Option<String>>(12)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| exit::not_found(format!("Token '{}' not found", name)))?;
+
+    println!("Name:          {}", token.name);
+    println!("Kind:          {}", token.kind.as_str());
+    println!(
+        "Expires:       {} ({})",
+        token.expires_at,
+        expiry_status(days_until(&token.expires_at)?)
+    );
+    println!(
+        "Last notified: {}",
+        token.last_notified.as_deref().unwrap_or("Never")
+    );
+    println!("Source:        {}", source.as_deref().unwrap_or("Manual"));
+    println!("Vendor:        {}", vendor.as_deref().unwrap_or("none"));
+    match cost {
+        Some(cost) => println!("Cost:          {:.2}", cost),
+        None => println!("Cost:          none"),
+    }
+    println!("Owner:         {}", owner.as_deref().unwrap_or("none"));
+    println!("Notes:         {}", notes.as_deref().unwrap_or("none"));
+    println!(
+        "Renew at:      {}",
+        renewal_url.as_deref().unwrap_or("none")
+    );
+    println!(
+        "Message:       {}",
+        token.message.as_deref().unwrap_or("none")
+    );
+    match (encrypted, reveal) {
+        (Some(encrypted), true) => println!("Secret:        {}", secret::decrypt(&encrypted)?),
+        (Some(_), false) => println!("Secret:        stored (use --reveal to show)"),
+        (None, _) => println!("Secret:        none"),
+    }
+
+    Ok(())
+}
+
+// Only the given options are changed
+fn edit_token(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    options: &EditOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut changes: Vec<(&str, Value)> = Vec::new();
+    if let Some(message) = &options.message {
+        changes.push(("message", Value::Text(message.clone())));
+    }
+    if options.clear_message {
+        changes.push(("message", Value::Null));
+    }
+    if let Some(kind) = options.kind {
+        changes.push(("kind", Value::Text(kind.as_str().to_string())));
+    }
+    if let Some(cost) = options.cost {
+        changes.push(("cost", Value::Real(cost)));
+    }
+    if options.clear_cost {
+        changes.push(("cost", Value::Null));
+    }
+    if let Some(vendor) = &options.vendor {
+        changes.push(("vendor", Value::Text(vendor.clone())));
+    }
+    if options.clear_vendor {
+        changes.push(("vendor", Value::Null));
+    }
+    for (column, value, clear) in [
+        ("owner", &options.owner, options.clear_owner),
+        ("notes", &options.notes, options.clear_notes),
+        (
+            "renewal_url",
+            &options.renewal_url,
+            options.clear_renewal_url,
+        ),
+    ] {
+        if let Some(value) = value {
+            changes.push((column, Value::Text(value.clone())));
+        }
+        if clear {
+            changes.push((column, Value::Null));
+        }
+    }
+    if changes.is_empty() {
+        return Err(exit::invalid("Nothing to change, see edit --help"));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for (column, value) in changes {
+        let updated = tx.execute(
+            &format!(
+                "UPDATE tokens SET {} = ?1
+                 WHERE tenant = ?2 AND name = ?3 AND deleted_at IS NULL",
+                column
+            ),
+            params![value, tenant, name],
+        )?;
+        if updated == 0 {
+            return Err(exit::not_found(format!("Token '{}' not found", name)));
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn set_secret(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    secret: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let updated = conn.execute(
+        "UPDATE tokens SET secret = ?1
+         WHERE tenant = ?2 AND name = ?3 AND deleted_at IS NULL",
+        params![secret, tenant, name],
     )?;
+    if updated == 0 {
+        return Err(exit::not_found(format!("Token '{}' not found", name)));
+    }
+    Ok(())
+}
 
-    let tokens = stmt
-        .query_map(params![now, threshold_days], |row| {
-            Ok(Token {
-                name: row.get(0)?,
-                expires_at: row.get(1)?,
-                last_notified: row.get(2)?,
-            })
-        })?
-        .collect::<SqlResult<Vec<_>>>()?;
+// Next page of tokens of all tenants expiring by `threshold`, soonest first, following
+// the (expires_at, tenant, name) of the previous page's last token. Reading a page at a
+// time keeps the inventory out of memory without holding a statement, and with it the
+// read lock, open while alerts go out.
+fn expiring_tokens_page(
+    conn: &Connection,
+    threshold: &str,
+    after: &(String, String, String),
+) -> SqlResult<Vec<Token>> {
+    // Dates are stored as YYYY-MM-DD, so comparing strings uses the expires_at index
+    conn.prepare_cached(&format!(
+        "SELECT {} FROM tokens
+         WHERE expires_at <= ?1 AND deleted_at IS NULL
+             AND (expires_at, tenant, name) > (?2, ?3, ?4)
+         ORDER BY expires_at, tenant, name
+         LIMIT ?5",
+        TOKEN_COLUMNS
+    ))?
+    .query_map(
+        params![
+            threshold,
+            after.0,
+            after.1,
+            after.2,
+            NOTIFY_BATCH_SIZE as i64
+        ],
+        token_from_row,
+    )?
+    .collect()
+}
 
-    Ok(tokens)
+// Channels that already delivered an alert for the token today
+fn delivered_channels(conn: &Connection, token: &Token) -> SqlResult<Vec<String>> {
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+    conn.prepare_cached(
+        "SELECT channel FROM deliveries
+         WHERE tenant = ?1 AND name = ?2 AND delivered_at >= ?3",
+    )?
+    .query_map(params![token.tenant, token.name, today], |row| row.get(0))?
+    .collect()
+}
+
+// Messages sent for the token by channels that edit in place, by delivery name
+fn sent_messages(conn: &Connection, token: &Token) -> SqlResult<HashMap<String, i64>> {
+    conn.prepare_cached(
+        "SELECT channel, message_id FROM deliveries
+         WHERE tenant = ?1 AND name = ?2 AND message_id IS NOT NULL",
+    )?
+    .query_map(params![token.tenant, token.name], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?
+    .collect()
+}
+
+fn record_delivery(
+    conn: &Connection,
+    token: &Token,
+    channel: &str,
+    message_id: Option<i64>,
+) -> SqlResult<()> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.prepare_cached(
+        "INSERT INTO deliveries (tenant, name, channel, delivered_at, message_id)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(tenant, name, channel) DO UPDATE SET
+             delivered_at = excluded.delivered_at,
+             message_id = excluded.message_id",
+    )?
+    .execute(params![token.tenant, token.name, channel, now, message_id])?;
+    Ok(())
+}
+
+// Identifies an alert by its token, countdown milestone and destination, see
+// notify::Channel::key
+fn alert_fingerprint(token: &Token, days_remaining: i64, channel_key: &str) -> String {
+    format!(
+        "{}\t{}\t{}\t{}",
+        token.tenant, token.name, days_remaining, channel_key
+    )
+}
+
+// Claims an alert before it is sent, so checks that overlap or rerun after a restart
+// within SUPPRESSION_WINDOW_SECONDS do not send it again. Returns false for a duplicate.
+fn claim_alert(conn: &Connection, config: &Config, fingerprint: &str) -> SqlResult<bool> {
+    let now = Utc::now();
+    let cutoff = (now - chrono::Duration::seconds(config.suppression_window_seconds))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    conn.prepare_cached("DELETE FROM sent_alerts WHERE fingerprint = ?1 AND sent_at < ?2")?
+        .execute(params![fingerprint, cutoff])?;
+    let claimed = conn
+        .prepare_cached("INSERT OR IGNORE INTO sent_alerts (fingerprint, sent_at) VALUES (?1, ?2)")?
+        .execute(params![
+            fingerprint,
+            now.format("%Y-%m-%d %H:%M:%S").to_string()
+        ])?;
+    Ok(claimed == 1)
 }
 
-fn update_last_notified(conn: &Connection, token_name: &str) -> SqlResult<()> {
+// A failed send is not a duplicate of its retry
+fn release_alert(conn: &Connection, fingerprint: &str) -> SqlResult<()> {
+    conn.prepare_cached("DELETE FROM sent_alerts WHERE fingerprint = ?1")?
+        .execute(params![fingerprint])?;
+    Ok(())
+}
+
+fn update_last_notified(conn: &Connection, token: &Token) -> SqlResult<()> {
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     conn.execute(
-        "UPDATE tokens SET last_notified = ?1 WHERE name = ?2",
-        params![now, token_name],
+        "UPDATE tokens SET last_notified = ?1 WHERE tenant = ?2 AND name = ?3",
+        params![now, token.tenant, token.name],
     )?;
     Ok(())
 }
 
 // Notification functions
 impl Config {
-    fn from_env() -> Result<Self, Box<dyn Error>> {
+    fn from_env(profile: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        // Profile settings take precedence, .env provides shared defaults
+        let (env_file, db_path) = profile_files(profile)?;
+        if profile.is_some() {
+            dotenv::from_filename(env_file).ok();
+        }
         dotenv::dotenv().ok(); // Load .env file if it exists
+        load_secret_files()?;
+
+        let notification_threshold_days = env::var("NOTIFICATION_THRESHOLD_DAYS")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()
+            .map_err(|_| "NOTIFICATION_THRESHOLD_DAYS must be a number")?;
 
         Ok(Self {
-            telegram_bot_token: env::var("TELEGRAM_BOT_TOKEN")
-                .map_err(|_| "TELEGRAM_BOT_TOKEN environment variable not set")?,
-            telegram_chat_id: env::var("TELEGRAM_CHAT_ID")
-                .map_err(|_| "TELEGRAM_CHAT_ID environment variable not set")?,
-            notification_threshold_days: env::var("NOTIFICATION_THRESHOLD_DAYS")
-                .unwrap_or_else(|_| "1".to_string())
-                .parse()
-                .map_err(|_| "NOTIFICATION_THRESHOLD_DAYS must be a number")?,
+            db_path,
+            channels: notify::channels_from_env("")?,
+            tenant_channels: notify::tenant_channels_from_env()?,
+            escalation_channels: notify::escalation_channels_from_env()?,
+            owner_channels: notify::owner_channels_from_env()?,
+            milestones: template::Milestones::from_env()?,
+            threshold_days: kind::thresholds_from_env(notification_threshold_days)?,
             check_interval_seconds: env::var("CHECK_INTERVAL_SECONDS")
                 .unwrap_or_else(|_| "3600".to_string())
                 .parse()
                 .map_err(|_| "CHECK_INTERVAL_SECONDS must be a number")?,
+            rescan_interval_seconds: env::var("RESCAN_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .map_err(|_| "RESCAN_INTERVAL_SECONDS must be a number")?,
+            notify_concurrency: match env::var("NOTIFY_CONCURRENCY")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse::<usize>()
+            {
+                Ok(0) | Err(_) => return Err("NOTIFY_CONCURRENCY must be a positive number".into()),
+                Ok(concurrency) => concurrency,
+            },
+            suppression_window_seconds: env::var("SUPPRESSION_WINDOW_SECONDS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "SUPPRESSION_WINDOW_SECONDS must be a number")?,
+            check_jitter_seconds: env::var("CHECK_JITTER_SECONDS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "CHECK_JITTER_SECONDS must be a number")?,
+            check_align: env::var("CHECK_ALIGN").is_ok_and(|value| value == "true" || value == "1"),
+            heartbeat_interval_days: env::var("HEARTBEAT_INTERVAL_DAYS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "HEARTBEAT_INTERVAL_DAYS must be a number")?,
+            owner_summary_interval_days: env::var("OWNER_SUMMARY_INTERVAL_DAYS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "OWNER_SUMMARY_INTERVAL_DAYS must be a number")?,
+            owner_summary_within_days: env::var("OWNER_SUMMARY_WITHIN_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| "OWNER_SUMMARY_WITHIN_DAYS must be a number")?,
+            notify_expiry_changes: env::var("NOTIFY_EXPIRY_CHANGES")
+                .is_ok_and(|value| value == "true" || value == "1"),
+            history_retention_days: env::var("HISTORY_RETENTION_DAYS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "HISTORY_RETENTION_DAYS must be a number")?,
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| "TRASH_RETENTION_DAYS must be a number")?,
+            maintenance_interval_days: env::var("MAINTENANCE_INTERVAL_DAYS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| "MAINTENANCE_INTERVAL_DAYS must be a number")?,
+            clock_check_source: env::var("CLOCK_CHECK_SOURCE").ok(),
+            clock_max_skew_seconds: env::var("CLOCK_MAX_SKEW_SECONDS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .map_err(|_| "CLOCK_MAX_SKEW_SECONDS must be a number")?,
+            healthcheck_url: env::var("HEALTHCHECK_URL").ok(),
+            healthcheck_fail_url: env::var("HEALTHCHECK_FAIL_URL").ok(),
+            http_listen_addr: env::var("HTTP_LISTEN_ADDR").ok(),
+            telegram_bot_commands: env::var("TELEGRAM_BOT_COMMANDS")
+                .is_ok_and(|value| value == "true" || value == "1"),
+            #[cfg(feature = "http-api")]
+            ingest_token: env::var("INGEST_TOKEN").ok(),
+            mqtt_host: env::var("MQTT_HOST").ok(),
+            #[cfg(feature = "mqtt")]
+            mqtt_port: env::var("MQTT_PORT")
+                .unwrap_or_else(|_| "1883".to_string())
+                .parse()
+                .map_err(|_| "MQTT_PORT must be a number")?,
+            #[cfg(feature = "mqtt")]
+            mqtt_username: env::var("MQTT_USERNAME").ok(),
+            #[cfg(feature = "mqtt")]
+            mqtt_password: env::var("MQTT_PASSWORD").ok(),
+            #[cfg(feature = "mqtt")]
+            mqtt_topic_prefix: env::var("MQTT_TOPIC_PREFIX")
+                .unwrap_or_else(|_| "expiration-notifier".to_string()),
+            hooks: hooks::Hooks::from_env(),
         })
     }
+
+    // Tenants without their own notification settings use the global channels
+    fn channels_for(&self, tenant: &str) -> &[notify::Channel] {
+        self.tenant_channels
+            .get(&notify::tenant_env_key(tenant))
+            .unwrap_or(&self.channels)
+    }
+
+    fn threshold_days(&self, kind: Kind) -> i64 {
+        self.threshold_days[&kind]
+    }
+}
+
+// The .env file and database of a profile, or the defaults
+fn profile_files(profile: Option<&str>) -> Result<(String, String), Box<dyn Error>> {
+    match profile {
+        Some(profile) => {
+            if !profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err("Profile names may only contain letters, digits, - and _".into());
+            }
+            Ok((
+                format!(".env.{}", profile),
+                format!("token_notifier.{}.db", profile),
+            ))
+        }
+        None => Ok((".env".to_string(), DB_NAME.to_string())),
+    }
+}
+
+// Docker and Kubernetes mount secrets as files. A variable set directly wins over its
+// _FILE variant.
+fn load_secret_files() -> Result<(), Box<dyn Error>> {
+    for (key, path) in env::vars() {
+        let Some(name) = key.strip_suffix("_FILE") else {
+            continue;
+        };
+        if !is_secret_var(name) || env::var_os(name).is_some() {
+            continue;
+        }
+        let value = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {} from {}: {}", name, path, e))?;
+        // SAFETY: configuration is loaded before any other thread is started
+        unsafe { env::set_var(name, value.trim_end_matches(['\r', '\n'])) };
+    }
+    Ok(())
+}
+
+// SECRET_VARS and OWNER_<KEY>_URLS, whose channel URLs embed credentials like NOTIFY_URLS
+fn is_secret_var(name: &str) -> bool {
+    SECRET_VARS.iter().any(|secret| name.ends_with(secret))
+        || (name.starts_with("OWNER_") && name.ends_with("_URLS"))
+}
+
+// Days from today until a YYYY-MM-DD expiry, zero or negative once expired
+fn days_until(expires_at: &str) -> chrono::ParseResult<i64> {
+    let expires_date = NaiveDate::parse_from_str(expires_at, "%Y-%m-%d")?;
+    let today = Local::now().date_naive();
+    Ok((expires_date - today).num_days())
+}
+
+// The milestone template or built-in alert, followed by the token's own message
+fn alert_text(config: &Config, token: &Token, days_remaining: i64) -> String {
+    let mut message = match config.milestones.for_days(days_remaining) {
+        Some(milestone) => template::expand(milestone, token, days_remaining),
+        None => alert_message(token.kind, &token.name, days_remaining),
+    };
+    if let Some(custom) = &token.message {
+        message.push('\n');
+        message.push_str(&template::expand(custom, token, days_remaining));
+    }
+    message
+}
+
+// Alerts get louder the longer an item stays expired
+fn alert_message(kind: Kind, name: &str, days_remaining: i64) -> String {
+    let label = kind.label();
+    let (expire, expired) = kind.verbs();
+    match days_remaining {
+        0 => format!("🚨 {} '{}' has {}!", label, name, expired.to_uppercase()),
+        days if days < -OVERDUE_ESCALATION_DAYS => format!(
+            "🔥 {} '{}' {} {} days ago and still has not been renewed!",
+            label, name, expired, -days
+        ),
+        days if days < 0 => format!(
+            "🚨 {} '{}' {} {} day{} ago!",
+            label,
+            name,
+            expired.to_uppercase(),
+            -days,
+            if days < -1 { "s" } else { "" }
+        ),
+        days => format!(
+            "⚠️ {} '{}' will {} in {} day{}!",
+            label,
+            name,
+            expire,
+            days,
+            if days > 1 { "s" } else { "" }
+        ),
+    }
+}
+
+// Outcome of a check, printed by `check`
+#[derive(Default, Serialize)]
+struct CheckReport {
+    tracked: usize,
+    expiring: usize,
+    expired: usize,
+    // Of all tracked items, None when nothing is tracked
+    min_days_remaining: Option<i64>,
+    // Items within their threshold, soonest first
+    items: Vec<CheckedItem>,
+}
+
+#[derive(Serialize)]
+struct CheckedItem {
+    tenant: String,
+    name: String,
+    kind: Kind,
+    expires_at: String,
+    days_remaining: i64,
+    // Channels tried this check; none when all of them already delivered today
+    notifications: Vec<NotificationResult>,
+}
+
+#[derive(Serialize)]
+struct NotificationResult {
+    channel: String,
+    sent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn check_and_notify(
+    conn: &Connection,
+    config: &Config,
+    mqtt: Option<&mqtt::Publisher>,
+) -> SqlResult<CheckReport> {
+    config.hooks.check_start();
+
+    let soonest: Option<String> = conn.query_row(
+        "SELECT MIN(expires_at) FROM tokens WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    let mut report = CheckReport {
+        tracked: conn.query_row(
+            "SELECT COUNT(*) FROM tokens WHERE deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?,
+        min_days_remaining: soonest
+            .map(|expires_at| days_until(&expires_at))
+            .transpose()
+            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?,
+        ..Default::default()
+    };
+    // Items are selected up to the longest threshold, then checked against their own
+    let max_threshold_days = config.threshold_days.values().copied().max().unwrap_or(0);
+    let threshold = (Utc::now().date_naive() + chrono::Duration::days(max_threshold_days))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut after = (String::new(), String::new(), String::new());
+    loop {
+        let page = expiring_tokens_page(conn, &threshold, &after)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = (
+            last.expires_at.clone(),
+            last.tenant.clone(),
+            last.name.clone(),
+        );
+        let last_page = page.len() < NOTIFY_BATCH_SIZE;
+
+        let mut batch = Vec::new();
+        for token in page {
+            let days_remaining = days_until(&token.expires_at)
+                .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+            if days_remaining > config.threshold_days(token.kind) {
+                continue;
+            }
+            if days_remaining <= 0 {
+                report.expired += 1;
+            } else {
+                report.expiring += 1;
+            }
+
+            let message = alert_text(config, &token, days_remaining);
+
+            if let Some(mqtt) = mqtt {
+                mqtt.publish_event(&token, days_remaining, &message);
+            }
+
+            let alert = notify::Alert {
+                message,
+                token: Some(token.name.clone()),
+                expires_at: Some(token.expires_at.clone()),
+                days_remaining: Some(days_remaining),
+            };
+            batch.push((token, alert));
+        }
+        dispatch(conn, config, &mut batch, &mut report)?;
+        if last_page {
+            break;
+        }
+    }
+    Ok(report)
 }
-fn send_telegram_notification(config: &Config, message: &str) -> Result<(), Box<dyn Error>> {
-    let client = Client::new();
-    let url = format!(
-        "https://api.telegram.org/bot{}/sendMessage",
-        config.telegram_bot_token
+
+// Status line and exit code in the Nagios plugin convention, also used by Zabbix
+fn nagios_status(report: &CheckReport) -> (String, i32) {
+    let (status, code) = if report.expired > 0 {
+        ("CRITICAL", 2)
+    } else if report.expiring > 0 {
+        ("WARNING", 1)
+    } else {
+        ("OK", 0)
+    };
+    let names = |expired: bool| -> Vec<&str> {
+        report
+            .items
+            .iter()
+            .filter(|item| (item.days_remaining <= 0) == expired)
+            .map(|item| item.name.as_str())
+            .collect()
+    };
+
+    let mut summary = format!("{} tracked", report.tracked);
+    if report.expired > 0 {
+        summary.push_str(&format!(
+            ", {} expired ({})",
+            report.expired,
+            names(true).join(", ")
+        ));
+    }
+    if report.expiring > 0 {
+        summary.push_str(&format!(
+            ", {} expiring ({})",
+            report.expiring,
+            names(false).join(", ")
+        ));
+    }
+    // Thresholds of 0 alert above zero, matching the WARNING and CRITICAL states
+    let mut perfdata = format!(
+        "tracked={} expiring={};0 expired={};;0",
+        report.tracked, report.expiring, report.expired
     );
+    if let Some(days) = report.min_days_remaining {
+        perfdata.push_str(&format!(" min_days_remaining={}", days));
+    }
+    (
+        format!("EXPIRATION {} - {} | {}", status, summary, perfdata),
+        code,
+    )
+}
 
-    let params = [
-        ("chat_id", config.telegram_chat_id.as_str()),
-        ("text", message),
-    ];
+fn print_check_report(report: &CheckReport, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        OutputFormat::Nagios => println!("{}", nagios_status(report).0),
+        OutputFormat::Text => {
+            println!(
+                "{} tracked, {} expiring, {} expired",
+                report.tracked, report.expiring, report.expired
+            );
+            for item in &report.items {
+                let sent: Vec<_> = item
+                    .notifications
+                    .iter()
+                    .map(|notification| {
+                        if notification.sent {
+                            notification.channel.clone()
+                        } else {
+                            format!("{} (failed)", notification.channel)
+                        }
+                    })
+                    .collect();
+                println!(
+                    "  {:<20} {:<15} {:<22} {}",
+                    item.name,
+                    item.expires_at,
+                    expiry_status(item.days_remaining),
+                    if sent.is_empty() {
+                        "already notified today".to_string()
+                    } else {
+                        sent.join(", ")
+                    }
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Sends a batch of alerts concurrently to the channels that have not delivered them
+// today; the connection is only used from this thread
+fn dispatch(
+    conn: &Connection,
+    config: &Config,
+    batch: &mut Vec<(Token, notify::Alert)>,
+    report: &mut CheckReport,
+) -> SqlResult<()> {
+    let mut tokens = Vec::new();
+    let mut deliveries = Vec::new();
+    for (token, alert) in batch.drain(..) {
+        report.items.push(CheckedItem {
+            tenant: token.tenant.clone(),
+            name: token.name.clone(),
+            kind: token.kind,
+            expires_at: token.expires_at.clone(),
+            days_remaining: alert.days_remaining.unwrap_or_default(),
+            notifications: Vec::new(),
+        });
+        let delivered = delivered_channels(conn, &token)?;
+        let days_remaining = alert.days_remaining.unwrap_or_default();
+        // Hooks run once a day per item like an alert, tracked as a delivery of their own
+        if config.hooks.on_expiry() && !delivered.iter().any(|name| name == HOOK_DELIVERY) {
+            config.hooks.expiring(&token, days_remaining);
+            record_delivery(conn, &token, HOOK_DELIVERY, None)?;
+        }
+        // Deliveries are named by channel key, so two chats or topics of one type are
+        // tracked apart. Expired items also go to the escalation channels, tracked under
+        // their own names so a channel used for both is not deduplicated.
+        let expired = alert.days_remaining.is_some_and(|days| days <= 0);
+        let escalation = config
+            .escalation_channels
+            .iter()
+            .filter(|_| expired)
+            .map(|channel| (channel, format!("escalation:{}", channel.key())));
+        let message_ids = sent_messages(conn, &token)?;
+        let mut pending = Vec::new();
+        let mut names = Vec::new();
+        let mut fingerprints = Vec::new();
+        for (channel, name) in config
+            .channels_for(&token.tenant)
+            .iter()
+            .map(|channel| (channel, channel.key()))
+            .chain(escalation)
+        {
+            if delivered.contains(&name) {
+                continue;
+            }
+            let fingerprint = alert_fingerprint(&token, days_remaining, &name);
+            if config.suppression_window_seconds > 0 && !claim_alert(conn, config, &fingerprint)? {
+                eprintln!(
+                    "Suppressed duplicate alert for '{}' via {}",
+                    token.name, name
+                );
+                continue;
+            }
+            pending.push((channel, message_ids.get(&name).copied()));
+            names.push(name);
+            fingerprints.push(fingerprint);
+        }
+        if !pending.is_empty() {
+            tokens.push((token, names, fingerprints, report.items.len() - 1));
+            deliveries.push((pending, alert));
+        }
+    }
+
+    let results = notify::send_all(&deliveries, config.notify_concurrency);
+    for ((token, names, fingerprints, item), results) in tokens.iter().zip(results) {
+        let mut notified = false;
+        for ((name, fingerprint), result) in names.iter().zip(fingerprints).zip(results) {
+            let error = match result {
+                Ok(message_id) => {
+                    record_delivery(conn, token, name, message_id)?;
+                    notified = true;
+                    None
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Failed to send notification for '{}' via {}: {}",
+                        token.name, name, e
+                    );
+                    if config.suppression_window_seconds > 0 {
+                        release_alert(conn, fingerprint)?;
+                    }
+                    Some(e)
+                }
+            };
+            report.items[*item].notifications.push(NotificationResult {
+                channel: name.clone(),
+                sent: error.is_none(),
+                error,
+            });
+        }
+        if notified {
+            update_last_notified(conn, token)?;
+        }
+    }
 
-    client.post(&url).form(&params).send()?;
     Ok(())
 }
 
-fn check_and_notify(conn: &Connection, config: &Config) -> SqlResult<()> {
-    let expiring_tokens = get_expiring_tokens(conn, config.notification_threshold_days)?;
+// Lets an external monitor alert when check cycles stop happening or keep failing
+#[cfg(feature = "http-client")]
+fn ping_healthcheck(config: &Config, succeeded: bool) -> Result<(), Box<dyn Error>> {
+    let url = if succeeded {
+        &config.healthcheck_url
+    } else {
+        &config.healthcheck_fail_url
+    };
+    match url {
+        Some(url) => http::ping(url),
+        None => Ok(()),
+    }
+}
 
-    for token in expiring_tokens {
-        let expires_date = NaiveDate::parse_from_str(&token.expires_at, "%Y-%m-%d")
-            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+#[cfg(not(feature = "http-client"))]
+fn ping_healthcheck(config: &Config, _: bool) -> Result<(), Box<dyn Error>> {
+    if config.healthcheck_url.is_some() || config.healthcheck_fail_url.is_some() {
+        return Err("HEALTHCHECK_URL is set but this build has no HTTP client".into());
+    }
+    Ok(())
+}
 
-        let today = Local::now().date_naive();
-        let days_remaining = (expires_date - today).num_days();
+// Periodic sign of life, so silence from the bot means nothing needs attention rather
+// than a dead daemon
+fn send_heartbeat(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+    if let Some(last) = get_meta(conn, "last_heartbeat")? {
+        let last = NaiveDateTime::parse_from_str(&last, "%Y-%m-%d %H:%M:%S")?;
+        if (now - last).num_days() < config.heartbeat_interval_days {
+            return Ok(());
+        }
+    }
+
+    let tokens = get_all_tokens(conn, None)?;
+    let (mut expiring, mut expired) = (0, 0);
+    for token in &tokens {
+        match days_until(&token.expires_at)? {
+            days if days <= 0 => expired += 1,
+            days if days <= config.threshold_days(token.kind) => expiring += 1,
+            _ => {}
+        }
+    }
+    let message = if expiring + expired == 0 {
+        format!(
+            "💚 Notifier alive: {} token(s) tracked, nothing critical",
+            tokens.len()
+        )
+    } else {
+        format!(
+            "💛 Notifier alive: {} token(s) tracked, {} expiring soon, {} expired",
+            tokens.len(),
+            expiring,
+            expired
+        )
+    };
+
+    notify::send(&config.channels, &notify::Alert::text(message))?;
+    set_meta(
+        conn,
+        "last_heartbeat",
+        &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )?;
+    Ok(())
+}
 
-        let message = if days_remaining <= 0 {
-            format!("🚨 Token '{}' has EXPIRED!", token.name)
+// Prunes history older than HISTORY_RETENTION_DAYS, tokens trashed longer than
+// TRASH_RETENTION_DAYS and deliveries of tokens that no longer exist, then compacts the
+// database and refreshes the query planner statistics. Returns the number of history,
+// token and delivery rows removed.
+fn run_maintenance(conn: &Connection, config: &Config) -> SqlResult<(usize, usize, usize)> {
+    let now = Utc::now();
+    let history = if config.history_retention_days > 0 {
+        let cutoff = (now - chrono::Duration::days(config.history_retention_days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        conn.execute("DELETE FROM history WHERE changed_at < ?1", params![cutoff])?
+    } else {
+        0
+    };
+    let trashed = trash::purge(conn, config.trash_retention_days)?;
+    let cutoff = (now - chrono::Duration::seconds(config.suppression_window_seconds))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    conn.execute(
+        "DELETE FROM sent_alerts WHERE sent_at < ?1",
+        params![cutoff],
+    )?;
+    let deliveries = conn.execute(
+        "DELETE FROM deliveries WHERE NOT EXISTS (
+             SELECT 1 FROM tokens
+             WHERE tokens.tenant = deliveries.tenant AND tokens.name = deliveries.name
+         )",
+        [],
+    )?;
+    conn.execute_batch("VACUUM; ANALYZE;")?;
+
+    set_meta(
+        conn,
+        "last_maintenance",
+        &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )?;
+    Ok((history, trashed, deliveries))
+}
+
+// Runs maintenance from the daemon every MAINTENANCE_INTERVAL_DAYS
+fn scheduled_maintenance(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+    if let Some(last) = get_meta(conn, "last_maintenance")? {
+        let last = NaiveDateTime::parse_from_str(&last, "%Y-%m-%d %H:%M:%S")?;
+        if (Utc::now().naive_utc() - last).num_days() < config.maintenance_interval_days {
+            return Ok(());
+        }
+    }
+    let (history, trashed, deliveries) = run_maintenance(conn, config)?;
+    println!(
+        "Maintenance: pruned {} history entries, {} trashed tokens and {} stale deliveries",
+        history, trashed, deliveries
+    );
+    Ok(())
+}
+
+// Starts announcing expiry changes from the current end of the history, rather than
+// announcing everything recorded before NOTIFY_EXPIRY_CHANGES was enabled
+fn track_expiry_changes(conn: &Connection) -> SqlResult<()> {
+    if get_meta(conn, "last_announced_change")?.is_none() {
+        let last_id: Option<i64> =
+            conn.query_row("SELECT MAX(id) FROM history", [], |row| row.get(0))?;
+        set_meta(
+            conn,
+            "last_announced_change",
+            &last_id.unwrap_or(0).to_string(),
+        )?;
+    }
+    Ok(())
+}
+
+// Sends the expiry changes recorded in the history since the last call, wherever they
+// came from. Shortened lifetimes are flagged, as they are the unexpected ones.
+fn notify_expiry_changes(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+    if !config.notify_expiry_changes {
+        return Ok(());
+    }
+    let last_id: i64 = get_meta(conn, "last_announced_change")?
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or(0);
+
+    let mut stmt = conn.prepare(
+        "SELECT h.id, h.tenant, h.name, h.old_expires_at, h.new_expires_at,
+                COALESCE(t.kind, 'token')
+         FROM history h
+         LEFT JOIN tokens t ON t.tenant = h.tenant AND t.name = h.name
+         WHERE h.id > ?1
+         ORDER BY h.id",
+    )?;
+    let changes = stmt
+        .query_map(params![last_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Kind>(5)?,
+            ))
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    for (id, tenant, name, old_expires_at, new_expires_at, kind) in changes {
+        let message = if new_expires_at < old_expires_at {
+            format!(
+                "⚠️ {} '{}' now expires EARLIER: {} → {}",
+                kind.label(),
+                name,
+                old_expires_at,
+                new_expires_at
+            )
         } else {
             format!(
-                "⚠️ Token '{}' will expire in {} day{}!",
-                token.name,
-                days_remaining,
-                if days_remaining > 1 { "s" } else { "" }
+                "📅 {} '{}' expiry changed: {} → {}",
+                kind.label(),
+                name,
+                old_expires_at,
+                new_expires_at
             )
         };
-
-        if let Err(e) = send_telegram_notification(config, &message) {
-            eprintln!("Failed to send notification: {}", e);
-        } else {
-            update_last_notified(conn, &token.name)?;
+        // A failed announcement is not retried, so one bad channel cannot repeat it
+        if let Err(e) = notify::send(config.channels_for(&tenant), &notify::Alert::text(message)) {
+            eprintln!("Failed to announce expiry change of '{}': {}", name, e);
         }
+        set_meta(conn, "last_announced_change", &id.to_string())?;
     }
+    Ok(())
+}
+
+fn get_meta(conn: &Connection, key: &str) -> SqlResult<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM meta WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+}
 
+fn set_meta(conn: &Connection, key: &str, value: &str) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
     Ok(())
 }
 
-fn run_daemon(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+fn list_channels(config: &Config) {
+    let enabled = |name: &str| config.channels.iter().any(|c| c.name() == name);
+
+    println!("{:<20} {:<8} {}", "Channel", "Enabled", "Path");
+    println!("{}", "-".repeat(50));
+    for name in notify::built_in() {
+        println!(
+            "{:<20} {:<8} {}",
+            name,
+            if enabled(name) { "yes" } else { "no" },
+            "(built-in)"
+        );
+    }
+    for (name, path) in notify::discover_plugins() {
+        println!(
+            "{:<20} {:<8} {}",
+            name,
+            if enabled(&name) { "yes" } else { "no" },
+            path.display()
+        );
+    }
+    if !config.escalation_channels.is_empty() {
+        let names: Vec<_> = config
+            .escalation_channels
+            .iter()
+            .map(|c| c.name())
+            .collect();
+        println!();
+        println!("Escalation for expired items: {}", names.join(", "));
+    }
+}
+
+#[cfg(feature = "http-api")]
+fn start_server(config: &Config, listen_addr: &str, read_only: bool) -> Result<(), Box<dyn Error>> {
+    if config.ingest_token.is_none() && !api_keys::any(&init_db(&config.db_path)?)? {
+        return Err(
+            "Set INGEST_TOKEN or create an API key with token-auth create to use HTTP_LISTEN_ADDR"
+                .into(),
+        );
+    }
+    server::spawn(
+        listen_addr,
+        &config.db_path,
+        config.ingest_token.clone(),
+        read_only,
+        config.hooks.clone(),
+    )
+}
+
+#[cfg(not(feature = "http-api"))]
+fn start_server(_: &Config, _: &str, _: bool) -> Result<(), Box<dyn Error>> {
+    Err("HTTP_LISTEN_ADDR is set but this build lacks the http-api feature".into())
+}
+
+#[cfg(feature = "telegram")]
+fn start_bot(config: &Config) -> Result<(), Box<dyn Error>> {
+    bot::spawn(config)
+}
+
+#[cfg(not(feature = "telegram"))]
+fn start_bot(_: &Config) -> Result<(), Box<dyn Error>> {
+    Err("TELEGRAM_BOT_COMMANDS is set but this build lacks the telegram feature".into())
+}
+
+fn run_daemon(conn: &Connection, config: &Config, read_only: bool) -> Result<(), Box<dyn Error>> {
     println!("Starting token expiration notifier daemon...");
     println!("Checking every {} seconds", config.check_interval_seconds);
-    println!(
-        "Notification threshold: {} days",
-        config.notification_threshold_days
-    );
+    let mut thresholds: Vec<_> = config
+        .threshold_days
+        .iter()
+        .map(|(kind, days)| format!("{} {}", kind.as_str(), days))
+        .collect();
+    thresholds.sort();
+    println!("Notification thresholds (days): {}", thresholds.join(", "));
+    for problem in config.milestones.problems() {
+        eprintln!("Warning: {}", problem);
+    }
+
+    if let Some(listen_addr) = &config.http_listen_addr {
+        start_server(config, listen_addr, read_only)?;
+    }
+    if config.telegram_bot_commands {
+        start_bot(config)?;
+    }
+
+    #[cfg(feature = "mqtt")]
+    let mqtt = config
+        .mqtt_host
+        .as_deref()
+        .map(|host| mqtt::Publisher::connect(config, host));
+    #[cfg(not(feature = "mqtt"))]
+    let mqtt: Option<mqtt::Publisher> = match config.mqtt_host {
+        Some(_) => return Err("MQTT_HOST is set but this build lacks the mqtt feature".into()),
+        None => None,
+    };
 
+    if let Err(e) = report_missed_checks(conn, config) {
+        eprintln!("Error reading the last check time: {}", e);
+    }
+
+    let mut last_rescan: Option<std::time::Instant> = None;
+    let mut last_status_date = None;
     loop {
-        if let Err(e) = check_and_notify(conn, config) {
-            eprintln!("Error checking tokens: {}", e);
+        // Re-scan imported sources first so renewals are picked up before alerting
+        let rescan_interval = std::time::Duration::from_secs(config.rescan_interval_seconds);
+        if !read_only
+            && config.rescan_interval_seconds > 0
+            && last_rescan.is_none_or(|at| at.elapsed() >= rescan_interval)
+        {
+            match scan::rescan_all(conn, &config.hooks) {
+                Ok(alerts) => {
+                    for (tenant, alert) in alerts {
+                        let channels = config.channels_for(&tenant);
+                        if let Err(e) = notify::send(channels, &notify::Alert::text(alert)) {
+                            eprintln!("Failed to send notification: {}", e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Error re-scanning sources: {}", e),
+            }
+            last_rescan = Some(std::time::Instant::now());
+        }
+
+        // Changes made through the API or a re-scan since the last check
+        if let Err(e) = notify_expiry_changes(conn, config) {
+            eprintln!("Error notifying expiry changes: {}", e);
+        }
+
+        let checked = match clock::verify(config) {
+            Ok(()) => check_and_notify(conn, config, mqtt.as_ref()).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match &checked {
+            Ok(_) => {
+                let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                if let Err(e) = set_meta(conn, "last_check", &now) {
+                    eprintln!("Error recording the check time: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error checking tokens: {}", e),
+        }
+        if let Err(e) = ping_healthcheck(config, checked.is_ok()) {
+            eprintln!("Failed to ping health check: {}", e);
+        }
+
+        if config.heartbeat_interval_days > 0
+            && let Err(e) = send_heartbeat(conn, config)
+        {
+            eprintln!("Error sending heartbeat: {}", e);
+        }
+
+        if config.owner_summary_interval_days > 0
+            && let Err(e) = owners::scheduled(conn, config)
+        {
+            eprintln!("Error sending owner summaries: {}", e);
+        }
+
+        // Trash is emptied on every cycle, so removed tokens are gone when `trash list`
+        // says even if maintenance is not scheduled
+        if !read_only {
+            match trash::purge(conn, config.trash_retention_days) {
+                Ok(0) => {}
+                Ok(purged) => println!("Deleted {} token(s) from the trash", purged),
+                Err(e) => eprintln!("Error emptying the trash: {}", e),
+            }
         }
 
+        if !read_only
+            && config.maintenance_interval_days > 0
+            && let Err(e) = scheduled_maintenance(conn, config)
+        {
+            eprintln!("Error running maintenance: {}", e);
+        }
+
+        // Daily retained status for dashboards
+        let today = Local::now().date_naive();
+        if let Some(mqtt) = &mqtt
+            && last_status_date != Some(today)
+        {
+            match get_all_tokens(conn, None) {
+                Ok(tokens) => {
+                    mqtt.publish_status(&tokens, &config.threshold_days);
+                    last_status_date = Some(today);
+                }
+                Err(e) => eprintln!("Error publishing MQTT status: {}", e),
+            }
+        }
+
+        wait_for_next_check(config);
+    }
+}
+
+// The daemon checks as soon as it starts; this says whether that check catches up on
+// checks scheduled while it was down
+fn report_missed_checks(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+    let Some(last) = get_meta(conn, "last_check")? else {
+        return Ok(());
+    };
+    let last = NaiveDateTime::parse_from_str(&last, "%Y-%m-%d %H:%M:%S")?;
+    let missed =
+        (Utc::now().naive_utc() - last).num_seconds() / config.check_interval_seconds.max(1) as i64;
+    if missed > 0 {
+        println!(
+            "Last check ran at {} UTC, {} scheduled check(s) missed, catching up now",
+            last, missed
+        );
+    }
+    Ok(())
+}
+
+// Sleeps until the next check is due by the wall clock. A plain sleep does not advance
+// while the host is suspended, which would delay the check by up to a full interval after
+// it resumes.
+fn wait_for_next_check(config: &Config) {
+    let due = Utc::now().timestamp() + next_check_delay(config).as_secs() as i64;
+    loop {
+        let remaining = due - Utc::now().timestamp();
+        if remaining <= 0 {
+            break;
+        }
         std::thread::sleep(std::time::Duration::from_secs(
-            config.check_interval_seconds,
+            remaining.min(WAKE_CHECK_SECONDS) as u64,
         ));
     }
+    let late = Utc::now().timestamp() - due;
+    if late > WAKE_CHECK_SECONDS {
+        println!("Resumed {}s after a scheduled check, catching up now", late);
+    }
+}
+
+// A full interval, or the time until the next multiple of the interval since the Unix
+// epoch when aligned, so hourly checks run on the hour. Random jitter keeps a fleet of
+// instances started at once from hitting the notification APIs together.
+fn next_check_delay(config: &Config) -> std::time::Duration {
+    let interval = config.check_interval_seconds.max(1);
+    let delay = if config.check_align {
+        interval - Utc::now().timestamp().unsigned_abs() % interval
+    } else {
+        interval
+    };
+    let jitter = match config.check_jitter_seconds {
+        0 => 0,
+        max => RandomState::new().build_hasher().finish() % (max + 1),
+    };
+    std::time::Duration::from_secs(delay + jitter)
 }
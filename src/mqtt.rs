@@ -0,0 +1,136 @@
+use chrono::Utc;
+use rumqttc::{Client, ClientError, MqttOptions, QoS};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Config, Kind, Token, days_until};
+
+// Messages waiting for the broker before new ones are dropped
+const QUEUE_CAPACITY: usize = 1000;
+
+// Publishes to <prefix>/events/<tenant>/<token> for every item within its threshold on
+// every check, and once a day retained states to <prefix>/tokens/<tenant>/<token> plus a
+// summary to <prefix>/status. The fixed namespaces keep token names such as "status" from
+// clashing with other topics.
+pub struct Publisher {
+    client: Client,
+    topic_prefix: String,
+}
+
+impl Publisher {
+    pub fn connect(config: &Config, host: &str) -> Self {
+        // Brokers drop a connected client when another connects with the same id, so
+        // profiles and hosts sharing a broker each get their own
+        let client_id = format!(
+            "expiration-notifier-{}-{:08x}",
+            process::id(),
+            Utc::now().timestamp_subsec_nanos()
+        );
+        let mut options = MqttOptions::new(client_id, host, config.mqtt_port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (&config.mqtt_username, &config.mqtt_password) {
+            options.set_credentials(username, password);
+        }
+
+        let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+        // The event loop must be driven for publishes to go out; it reconnects on error
+        thread::spawn(move || {
+            for event in connection.iter() {
+                if let Err(e) = event {
+                    eprintln!("MQTT connection error: {}", e);
+                    thread::sleep(Duration::from_secs(5));
+                }
+            }
+        });
+
+        Self {
+            client,
+            topic_prefix: config.mqtt_topic_prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn publish_event(&self, token: &Token, days_remaining: i64, message: &str) {
+        let topic = format!("{}/events/{}", self.topic_prefix, token_topic(token));
+        let payload = json!({
+            "tenant": token.tenant,
+            "name": token.name,
+            "expires_at": token.expires_at,
+            "days_remaining": days_remaining,
+            "message": message,
+        });
+        self.publish(topic, false, payload);
+    }
+
+    pub fn publish_status(&self, tokens: &[Token], threshold_days: &HashMap<Kind, i64>) {
+        let mut expiring = 0;
+        let mut expired = 0;
+
+        for token in tokens {
+            let Ok(days_remaining) = days_until(&token.expires_at) else {
+                continue;
+            };
+            if days_remaining <= 0 {
+                expired += 1;
+            } else if days_remaining <= threshold_days[&token.kind] {
+                expiring += 1;
+            }
+
+            let topic = format!("{}/tokens/{}", self.topic_prefix, token_topic(token));
+            let payload = json!({
+                "tenant": token.tenant,
+                "name": token.name,
+                "kind": token.kind,
+                "expires_at": token.expires_at,
+                "days_remaining": days_remaining,
+                "expired": days_remaining <= 0,
+            });
+            self.publish(topic, true, payload);
+        }
+
+        let payload = json!({
+            "tracked": tokens.len(),
+            "expiring": expiring,
+            "expired": expired,
+            "updated_at": Utc::now().to_rfc3339(),
+        });
+        self.publish(format!("{}/status", self.topic_prefix), true, payload);
+    }
+
+    // Never blocks, so an unreachable broker cannot stall checks; messages that do not
+    // fit in the queue are dropped
+    fn publish(&self, topic: String, retain: bool, payload: Value) {
+        match self
+            .client
+            .try_publish(&topic, QoS::AtLeastOnce, retain, payload.to_string())
+        {
+            Ok(()) => {}
+            Err(ClientError::TryRequest(_)) => {
+                eprintln!("MQTT queue is full, dropped the message to {}", topic)
+            }
+            Err(e) => eprintln!("Failed to publish MQTT message: {}", e),
+        }
+    }
+}
+
+// <tenant>/<token>, always with the tenant so topics of different tenants cannot overlap
+fn token_topic(token: &Token) -> String {
+    format!(
+        "{}/{}",
+        topic_segment(&token.tenant),
+        topic_segment(&token.name)
+    )
+}
+
+// Token names may contain MQTT wildcards and level separators
+fn topic_segment(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '+' | '#' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
@@ -0,0 +1,858 @@
+#[cfg(feature = "telegram")]
+use reqwest::blocking::multipart::{Form, Part};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const PLUGIN_PREFIX: &str = "expiration-notifier-notify-";
+
+#[cfg(feature = "telegram")]
+const DEFAULT_CHANNELS: &str = "telegram";
+#[cfg(not(feature = "telegram"))]
+const DEFAULT_CHANNELS: &str = "";
+
+// Settings a tenant can override with TENANT_<NAME>_<SETTING>
+const TENANT_SETTINGS: &[&str] = &[
+    "NOTIFY_CHANNELS",
+    "NOTIFY_URLS",
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_CHAT_ID",
+    "TELEGRAM_ATTACH_CALENDAR",
+    "TELEGRAM_EDIT_IN_PLACE",
+    "TELEGRAM_PIN_MESSAGES",
+];
+
+// Settings a tenant inherits when it does not set them. Destinations and channel
+// choices never fall back, so a tenant's alerts only go where the tenant says.
+const SHARED_SETTINGS: &[&str] = &["TELEGRAM_BOT_TOKEN"];
+
+// Notification channel selected through NOTIFY_CHANNELS or NOTIFY_URLS
+#[derive(Debug)]
+pub enum Channel {
+    #[cfg(feature = "telegram")]
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+        // Follow token alerts with an .ics invite for the expiry date
+        attach_calendar: bool,
+        // Update the token's previous alert instead of posting a new one
+        edit_in_place: bool,
+        pin_messages: bool,
+    },
+    // Topic URL, e.g. https://ntfy.sh/my-topic
+    #[cfg(feature = "ntfy")]
+    Ntfy {
+        url: String,
+        credentials: Option<(String, String)>,
+    },
+    #[cfg(feature = "gotify")]
+    Gotify {
+        url: String,
+        token: String,
+    },
+    #[cfg(feature = "slack")]
+    Slack {
+        webhook_url: String,
+    },
+    // Receives the alert as a JSON POST
+    #[cfg(feature = "webhook")]
+    Webhook {
+        url: String,
+    },
+    // Mail handed to the local sendmail, which relays it
+    #[cfg(feature = "email")]
+    Email {
+        to: String,
+        from: Option<String>,
+    },
+    // Executable named expiration-notifier-notify-<name> found on PATH
+    Plugin {
+        name: String,
+        path: PathBuf,
+    },
+}
+
+// Alert handed to channels; plugins receive it as JSON on stdin
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub message: String,
+    pub token: Option<String>,
+    pub expires_at: Option<String>,
+    pub days_remaining: Option<i64>,
+}
+
+impl Alert {
+    // Alert not tied to a single token's expiry
+    pub fn text(message: String) -> Self {
+        Self {
+            message,
+            token: None,
+            expires_at: None,
+            days_remaining: None,
+        }
+    }
+}
+
+impl Channel {
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "telegram")]
+            Channel::Telegram { .. } => "telegram",
+            #[cfg(feature = "ntfy")]
+            Channel::Ntfy { .. } => "ntfy",
+            #[cfg(feature = "gotify")]
+            Channel::Gotify { .. } => "gotify",
+            #[cfg(feature = "slack")]
+            Channel::Slack { .. } => "slack",
+            #[cfg(feature = "webhook")]
+            Channel::Webhook { .. } => "webhook",
+            #[cfg(feature = "email")]
+            Channel::Email { .. } => "email",
+            Channel::Plugin { name, .. } => name,
+        }
+    }
+
+    // Identifies the configured destination, unlike name(): two tgram:// URLs are both
+    // "telegram". URLs and tokens that may hold secrets are hashed.
+    pub fn key(&self) -> String {
+        match self {
+            #[cfg(feature = "telegram")]
+            Channel::Telegram { chat_id, .. } => format!("telegram:{}", chat_id),
+            #[cfg(feature = "ntfy")]
+            Channel::Ntfy { url, .. } => format!("ntfy:{:016x}", fnv1a(url)),
+            #[cfg(feature = "gotify")]
+            Channel::Gotify { url, token } => {
+                format!("gotify:{:016x}", fnv1a(&format!("{}/{}", url, token)))
+            }
+            #[cfg(feature = "slack")]
+            Channel::Slack { webhook_url } => format!("slack:{:016x}", fnv1a(webhook_url)),
+            #[cfg(feature = "webhook")]
+            Channel::Webhook { url } => format!("webhook:{:016x}", fnv1a(url)),
+            #[cfg(feature = "email")]
+            Channel::Email { to, .. } => format!("email:{}", to),
+            Channel::Plugin { name, .. } => name.clone(),
+        }
+    }
+
+    // Edits the previously sent message when given one and the channel edits in place.
+    // Returns the id of the message to edit next time.
+    fn send(&self, alert: &Alert, message_id: Option<i64>) -> Result<Option<i64>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "telegram")]
+            Channel::Telegram {
+                bot_token,
+                chat_id,
+                attach_calendar,
+                edit_in_place,
+                pin_messages,
+            } => {
+                if *edit_in_place && let Some(message_id) = message_id {
+                    match edit_telegram(bot_token, chat_id, message_id, &alert.message) {
+                        Ok(()) => return Ok(Some(message_id)),
+                        // Deleted or too old to edit, post a new one
+                        Err(e) => eprintln!("Failed to edit Telegram message: {}", e),
+                    }
+                }
+
+                let message_id = send_telegram(bot_token, chat_id, &alert.message)?;
+                if *pin_messages {
+                    pin_telegram(bot_token, chat_id, message_id)?;
+                }
+                if *attach_calendar
+                    && let (Some(token), Some(expires_at)) = (&alert.token, &alert.expires_at)
+                {
+                    send_telegram_calendar(bot_token, chat_id, token, expires_at)?;
+                }
+                Ok(edit_in_place.then_some(message_id))
+            }
+            #[cfg(feature = "ntfy")]
+            Channel::Ntfy { url, credentials } => {
+                send_ntfy(url, credentials.as_ref(), alert).map(|()| None)
+            }
+            #[cfg(feature = "gotify")]
+            Channel::Gotify { url, token } => send_gotify(url, token, alert).map(|()| None),
+            #[cfg(feature = "slack")]
+            Channel::Slack { webhook_url } => send_slack(webhook_url, alert).map(|()| None),
+            #[cfg(feature = "webhook")]
+            Channel::Webhook { url } => send_webhook(url, alert).map(|()| None),
+            #[cfg(feature = "email")]
+            Channel::Email { to, from } => send_email(to, from.as_deref(), alert).map(|()| None),
+            Channel::Plugin { path, .. } => send_plugin(path, alert).map(|()| None),
+        }
+    }
+}
+
+// NOTIFY_CHANNELS is a comma-separated list of channel names, "telegram" by default
+// unless NOTIFY_URLS configures the channels instead. Settings are read with the given
+// prefix, only SHARED_SETTINGS falling back to the global ones.
+pub fn channels_from_env(prefix: &str) -> Result<Vec<Channel>, Box<dyn Error>> {
+    channels_from_vars(prefix, |name| env::var(name).ok())
+}
+
+fn channels_from_vars(
+    prefix: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<Channel>, Box<dyn Error>> {
+    let var = |name: &str| {
+        lookup(&format!("{}{}", prefix, name))
+            .or_else(|| SHARED_SETTINGS.contains(&name).then(|| lookup(name))?)
+    };
+    #[cfg(feature = "telegram")]
+    let flag = |name: &str| var(name).is_some_and(|value| value == "true" || value == "1");
+    let urls = var("NOTIFY_URLS");
+    let names = var("NOTIFY_CHANNELS").unwrap_or_else(|| match urls {
+        Some(_) => String::new(),
+        None => DEFAULT_CHANNELS.to_string(),
+    });
+    let mut channels = Vec::new();
+
+    for name in names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let channel = match name {
+            #[cfg(feature = "telegram")]
+            "telegram" => Channel::Telegram {
+                bot_token: var("TELEGRAM_BOT_TOKEN")
+                    .ok_or("TELEGRAM_BOT_TOKEN environment variable not set")?,
+                chat_id: var("TELEGRAM_CHAT_ID").ok_or_else(|| {
+                    format!("{}TELEGRAM_CHAT_ID environment variable not set", prefix)
+                })?,
+                attach_calendar: flag("TELEGRAM_ATTACH_CALENDAR"),
+                edit_in_place: flag("TELEGRAM_EDIT_IN_PLACE"),
+                pin_messages: flag("TELEGRAM_PIN_MESSAGES"),
+            },
+            _ => Channel::Plugin {
+                name: name.to_string(),
+                path: find_plugin(name).ok_or_else(|| {
+                    format!(
+                        "Unknown notification channel '{}': no {}{} on PATH",
+                        name, PLUGIN_PREFIX, name
+                    )
+                })?,
+            },
+        };
+        channels.push(channel);
+    }
+
+    for url in urls
+        .iter()
+        .flat_map(|urls| urls.split([',', ' ']))
+        .filter(|url| !url.is_empty())
+    {
+        channels.push(channel_from_url(url)?);
+    }
+
+    Ok(channels)
+}
+
+// ESCALATION_URLS, notified in addition to the regular channels once an item has expired
+pub fn escalation_channels_from_env() -> Result<Vec<Channel>, Box<dyn Error>> {
+    env::var("ESCALATION_URLS")
+        .unwrap_or_default()
+        .split([',', ' '])
+        .filter(|url| !url.is_empty())
+        .map(channel_from_url)
+        .collect()
+}
+
+// Where each owner's summaries go, from OWNER_<KEY>_URLS, keyed by owner_env_key
+pub fn owner_channels_from_env() -> Result<HashMap<String, Vec<Channel>>, Box<dyn Error>> {
+    let mut owners = HashMap::new();
+    for (key, urls) in env::vars() {
+        let Some(owner) = key
+            .strip_prefix("OWNER_")
+            .and_then(|rest| rest.strip_suffix("_URLS"))
+        else {
+            continue;
+        };
+        let channels = urls
+            .split([',', ' '])
+            .filter(|url| !url.is_empty())
+            .map(channel_from_url)
+            .collect::<Result<Vec<_>, _>>()?;
+        owners.insert(owner.to_string(), channels);
+    }
+    Ok(owners)
+}
+
+// "@alice" and "alice" are configured through OWNER_ALICE_URLS
+pub fn owner_env_key(owner: &str) -> String {
+    tenant_env_key(owner.trim().trim_start_matches('@'))
+}
+
+// Channel types compiled into this build
+pub fn built_in() -> Vec<&'static str> {
+    [
+        ("telegram", cfg!(feature = "telegram")),
+        ("ntfy", cfg!(feature = "ntfy")),
+        ("gotify", cfg!(feature = "gotify")),
+        ("slack", cfg!(feature = "slack")),
+        ("webhook", cfg!(feature = "webhook")),
+        ("email", cfg!(feature = "email")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+// Apprise-style URL such as tgram://<bot_token>/<chat_id> or ntfy://ntfy.sh/<topic>.
+// Schemes ending in "s" use HTTPS.
+pub fn channel_from_url(url: &str) -> Result<Channel, Box<dyn Error>> {
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| format!("Invalid notification URL '{}'", url))?;
+
+    let channel = match scheme {
+        #[cfg(feature = "telegram")]
+        "tgram" => {
+            let invalid = || format!("Invalid {}:// notification URL", scheme);
+            let (bot_token, chat_id) = rest.split_once('/').ok_or_else(invalid)?;
+            Channel::Telegram {
+                bot_token: bot_token.to_string(),
+                chat_id: chat_id.trim_end_matches('/').to_string(),
+                attach_calendar: false,
+                edit_in_place: false,
+                pin_messages: false,
+            }
+        }
+        #[cfg(feature = "ntfy")]
+        "ntfy" | "ntfys" => {
+            let invalid = || format!("Invalid {}:// notification URL", scheme);
+            let (credentials, rest) = match rest.rsplit_once('@') {
+                Some((userinfo, rest)) => {
+                    let (user, password) = userinfo.split_once(':').ok_or_else(invalid)?;
+                    (Some((user.to_string(), password.to_string())), rest)
+                }
+                None => (None, rest),
+            };
+            // A bare topic is published on ntfy.sh
+            let url = if rest.contains('/') {
+                format!("{}://{}", http_scheme(scheme), rest)
+            } else {
+                format!("https://ntfy.sh/{}", rest)
+            };
+            Channel::Ntfy { url, credentials }
+        }
+        #[cfg(feature = "gotify")]
+        "gotify" | "gotifys" => {
+            let invalid = || format!("Invalid {}:// notification URL", scheme);
+            let (host, token) = rest
+                .trim_end_matches('/')
+                .rsplit_once('/')
+                .ok_or_else(invalid)?;
+            Channel::Gotify {
+                url: format!("{}://{}", http_scheme(scheme), host),
+                token: token.to_string(),
+            }
+        }
+        #[cfg(feature = "slack")]
+        "slack" => {
+            if rest.split('/').filter(|part| !part.is_empty()).count() != 3 {
+                return Err(format!("Invalid {}:// notification URL", scheme).into());
+            }
+            Channel::Slack {
+                webhook_url: format!("https://hooks.slack.com/services/{}", rest),
+            }
+        }
+        #[cfg(feature = "webhook")]
+        "json" | "jsons" => Channel::Webhook {
+            url: format!("{}://{}", http_scheme(scheme), rest),
+        },
+        // mailto://<recipient>[?from=<sender>]
+        #[cfg(feature = "email")]
+        "mailto" => {
+            let invalid = || format!("Invalid {}:// notification URL", scheme);
+            let (to, query) = rest.split_once('?').unwrap_or((rest, ""));
+            let from = match query.strip_prefix("from=") {
+                Some(from) => Some(from),
+                None if query.is_empty() => None,
+                None => return Err(invalid().into()),
+            };
+            if !is_address(to) || !from.is_none_or(is_address) {
+                return Err(invalid().into());
+            }
+            Channel::Email {
+                to: to.to_string(),
+                from: from.map(str::to_string),
+            }
+        }
+        // The relay sendmail hands mail to decides about TLS
+        #[cfg(feature = "email")]
+        "mailtos" => {
+            return Err("mailtos:// is not supported, configure TLS in the sendmail relay".into());
+        }
+        _ => return Err(format!("Unsupported notification URL scheme '{}'", scheme).into()),
+    };
+    Ok(channel)
+}
+
+// FNV-1a, which unlike the standard library's hasher is stable across Rust releases
+#[cfg(any(
+    feature = "ntfy",
+    feature = "gotify",
+    feature = "slack",
+    feature = "webhook"
+))]
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// A single address that cannot inject headers or sendmail options
+#[cfg(feature = "email")]
+fn is_address(address: &str) -> bool {
+    let Some((user, domain)) = address.split_once('@') else {
+        return false;
+    };
+    !user.is_empty()
+        && !user.starts_with('-')
+        && !domain.is_empty()
+        && !domain.contains('@')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, ',' | '<' | '>'))
+}
+
+#[cfg(any(feature = "ntfy", feature = "gotify", feature = "webhook"))]
+fn http_scheme(scheme: &str) -> &'static str {
+    if scheme.ends_with('s') {
+        "https"
+    } else {
+        "http"
+    }
+}
+
+// Channels of every tenant that overrides a notification setting, keyed by
+// tenant_env_key
+pub fn tenant_channels_from_env() -> Result<HashMap<String, Vec<Channel>>, Box<dyn Error>> {
+    let mut tenants = HashMap::new();
+
+    for (key, _) in env::vars() {
+        let Some(rest) = key.strip_prefix("TENANT_") else {
+            continue;
+        };
+        let tenant = TENANT_SETTINGS.iter().find_map(|setting| {
+            rest.strip_suffix(setting)
+                .and_then(|tenant| tenant.strip_suffix('_'))
+        });
+        if let Some(tenant) = tenant
+            && !tenants.contains_key(tenant)
+        {
+            let channels = channels_from_env(&format!("TENANT_{}_", tenant))?;
+            tenants.insert(tenant.to_string(), channels);
+        }
+    }
+
+    Ok(tenants)
+}
+
+// "team-a" is configured through TENANT_TEAM_A_* variables
+pub fn tenant_env_key(tenant: &str) -> String {
+    tenant
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Sends the alert to every channel, failing if any of them failed
+pub fn send(channels: &[Channel], alert: &Alert) -> Result<(), Box<dyn Error>> {
+    let mut failures = Vec::new();
+    for channel in channels {
+        if let Err(e) = channel.send(alert, None) {
+            failures.push(format!("{}: {}", channel.name(), describe(e)));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; ").into())
+    }
+}
+
+// Error message safe to print: HTTP client errors name the request URL, which holds the
+// bot token for Telegram and the credentials of other channel URLs
+fn describe(error: Box<dyn Error>) -> String {
+    #[cfg(feature = "http-client")]
+    let error = match error.downcast::<reqwest::Error>() {
+        Ok(error) => return error.without_url().to_string(),
+        Err(error) => error,
+    };
+    error.to_string()
+}
+
+// Sends each alert to its channels with at most `concurrency` alerts in flight,
+// returning the outcome for every channel of every delivery in order. Each channel comes
+// with the message it sent last time, if any.
+pub fn send_all(
+    deliveries: &[(Vec<(&Channel, Option<i64>)>, Alert)],
+    concurrency: usize,
+) -> Vec<Vec<Result<Option<i64>, String>>> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Vec<Result<Option<i64>, String>>>> =
+        Mutex::new(vec![Vec::new(); deliveries.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.min(deliveries.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((channels, alert)) = deliveries.get(index) else {
+                        break;
+                    };
+                    let outcome = channels
+                        .iter()
+                        .map(|(channel, message_id)| {
+                            channel.send(alert, *message_id).map_err(describe)
+                        })
+                        .collect();
+                    results.lock().expect("notification worker panicked")[index] = outcome;
+                }
+            });
+        }
+    });
+
+    results.into_inner().expect("notification worker panicked")
+}
+
+#[cfg(feature = "telegram")]
+// Returns the id of the sent message
+pub fn send_telegram(bot_token: &str, chat_id: &str, message: &str) -> Result<i64, Box<dyn Error>> {
+    let client = crate::http::client()?;
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
+
+    let params = [("chat_id", chat_id), ("text", message)];
+
+    let response: serde_json::Value = client
+        .post(&url)
+        .form(&params)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(response["result"]["message_id"]
+        .as_i64()
+        .ok_or("Telegram response has no message_id")?)
+}
+
+#[cfg(feature = "telegram")]
+fn edit_telegram(
+    bot_token: &str,
+    chat_id: &str,
+    message_id: i64,
+    message: &str,
+) -> Result<(), Box<dyn Error>> {
+    let url = format!("https://api.telegram.org/bot{}/editMessageText", bot_token);
+    let message_id = message_id.to_string();
+    let params = [
+        ("chat_id", chat_id),
+        ("message_id", &message_id),
+        ("text", message),
+    ];
+
+    let response = crate::http::client()?.post(&url).form(&params).send()?;
+    if !response.status().is_success() {
+        let body = response.text()?;
+        // Telegram rejects edits that leave the text unchanged
+        if !body.contains("message is not modified") {
+            return Err(format!("Telegram API error: {}", body).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "telegram")]
+fn pin_telegram(bot_token: &str, chat_id: &str, message_id: i64) -> Result<(), Box<dyn Error>> {
+    let url = format!("https://api.telegram.org/bot{}/pinChatMessage", bot_token);
+    let message_id = message_id.to_string();
+    let params = [
+        ("chat_id", chat_id),
+        ("message_id", message_id.as_str()),
+        ("disable_notification", "true"),
+    ];
+
+    crate::http::client()?
+        .post(&url)
+        .form(&params)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "telegram")]
+fn send_telegram_calendar(
+    bot_token: &str,
+    chat_id: &str,
+    token: &str,
+    expires_at: &str,
+) -> Result<(), Box<dyn Error>> {
+    let event = crate::calendar::expiry_event(token, expires_at)?;
+    let document = Part::text(event)
+        .file_name(format!("{}.ics", crate::calendar::file_stem(token)))
+        .mime_str("text/calendar")?;
+    let form = Form::new()
+        .text("chat_id", chat_id.to_string())
+        .part("document", document);
+
+    crate::http::client()?
+        .post(format!(
+            "https://api.telegram.org/bot{}/sendDocument",
+            bot_token
+        ))
+        .multipart(form)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "ntfy")]
+fn send_ntfy(
+    url: &str,
+    credentials: Option<&(String, String)>,
+    alert: &Alert,
+) -> Result<(), Box<dyn Error>> {
+    let mut request = crate::http::client()?
+        .post(url)
+        .header("Title", "Expiration notifier")
+        .body(alert.message.clone());
+    if let Some((user, password)) = credentials {
+        request = request.basic_auth(user, Some(password));
+    }
+    request.send()?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "gotify")]
+fn send_gotify(url: &str, token: &str, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    // Expired tokens get a high priority so they break through do-not-disturb
+    let priority = match alert.days_remaining {
+        Some(days) if days <= 0 => 8,
+        _ => 5,
+    };
+    crate::http::client()?
+        .post(format!("{}/message", url))
+        .header("X-Gotify-Key", token)
+        .json(&serde_json::json!({
+            "title": "Expiration notifier",
+            "message": alert.message,
+            "priority": priority,
+        }))
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "slack")]
+fn send_slack(webhook_url: &str, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    crate::http::client()?
+        .post(webhook_url)
+        .json(&serde_json::json!({ "text": alert.message }))
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "webhook")]
+fn send_webhook(url: &str, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    crate::http::client()?
+        .post(url)
+        .json(alert)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "email")]
+fn send_email(to: &str, from: Option<&str>, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    let mut sendmail = Command::new("sendmail");
+    // -i keeps a line with a single "." from ending the message
+    sendmail.arg("-i");
+    if let Some(from) = from {
+        sendmail.args(["-f", from]);
+    }
+    let mut child = sendmail
+        .args(["--", to])
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()
+        .map_err(|e| format!("Failed to run sendmail: {}", e))?;
+
+    // sendmail takes local line endings and converts them for SMTP
+    let mut message = format!("To: {}\n", to);
+    if let Some(from) = from {
+        message.push_str(&format!("From: {}\n", from));
+    }
+    message.push_str(
+        "Subject: Expiration notifier\n\
+         MIME-Version: 1.0\n\
+         Content-Type: text/plain; charset=utf-8\n\
+         Content-Transfer-Encoding: 8bit\n\n",
+    );
+    message.push_str(&alert.message);
+    message.push('\n');
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open sendmail stdin")?
+        .write_all(message.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("sendmail exited with {}", status).into());
+    }
+    Ok(())
+}
+
+fn send_plugin(path: &Path, alert: &Alert) -> Result<(), Box<dyn Error>> {
+    // Plugin output must not end up in the check report on stdout
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open plugin stdin")?
+        .write_all(serde_json::to_string(alert)?.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", path.display(), status).into());
+    }
+    Ok(())
+}
+
+// Plugins on PATH by channel name, first match wins like the shell
+pub fn discover_plugins() -> Vec<(String, PathBuf)> {
+    let mut plugins: Vec<(String, PathBuf)> = Vec::new();
+
+    for dir in env::split_paths(&env::var_os("PATH").unwrap_or_default()) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let Some(name) = file_name.strip_prefix(PLUGIN_PREFIX) else {
+                continue;
+            };
+            let path = entry.path();
+            if is_executable(&path) && !plugins.iter().any(|(known, _)| known == name) {
+                plugins.push((name.to_string(), path));
+            }
+        }
+    }
+
+    plugins.sort();
+    plugins
+}
+
+fn find_plugin(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(format!("{}{}", PLUGIN_PREFIX, name)))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "telegram", feature = "webhook"))]
+    fn tenant_channels(vars: &[(&str, &str)]) -> Vec<super::Channel> {
+        let vars: std::collections::HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        super::channels_from_vars("TENANT_PAYMENTS_", |name| vars.get(name).cloned()).unwrap()
+    }
+
+    #[cfg(all(feature = "telegram", feature = "webhook"))]
+    #[test]
+    fn tenant_chat_gets_no_global_destinations() {
+        let channels = tenant_channels(&[
+            ("TELEGRAM_BOT_TOKEN", "123:global"),
+            ("TELEGRAM_CHAT_ID", "-100111"),
+            ("NOTIFY_CHANNELS", "telegram"),
+            ("NOTIFY_URLS", "json://alerts.example.com/hook"),
+            ("TENANT_PAYMENTS_TELEGRAM_CHAT_ID", "-100222"),
+        ]);
+        let keys: Vec<String> = channels.iter().map(super::Channel::key).collect();
+        assert_eq!(keys, ["telegram:-100222"]);
+        let super::Channel::Telegram { bot_token, .. } = &channels[0] else {
+            panic!("expected a Telegram channel");
+        };
+        assert_eq!(bot_token, "123:global");
+    }
+
+    #[cfg(all(feature = "telegram", feature = "webhook"))]
+    #[test]
+    fn tenant_urls_replace_global_channels() {
+        let channels = tenant_channels(&[
+            ("TELEGRAM_BOT_TOKEN", "123:global"),
+            ("TELEGRAM_CHAT_ID", "-100111"),
+            ("NOTIFY_CHANNELS", "telegram"),
+            (
+                "TENANT_PAYMENTS_NOTIFY_URLS",
+                "json://payments.example.com/hook",
+            ),
+        ]);
+        let names: Vec<&str> = channels.iter().map(super::Channel::name).collect();
+        assert_eq!(names, ["webhook"]);
+    }
+
+    #[cfg(feature = "email")]
+    #[test]
+    fn parses_mailto_urls() {
+        use super::{Channel, channel_from_url};
+
+        let Channel::Email { to, from } = channel_from_url("mailto://ops@example.com").unwrap()
+        else {
+            panic!("expected an email channel");
+        };
+        assert_eq!((to.as_str(), from), ("ops@example.com", None));
+
+        let channel = channel_from_url("mailto://ops@example.com?from=alerts@example.com");
+        let Channel::Email { from, .. } = channel.unwrap() else {
+            panic!("expected an email channel");
+        };
+        assert_eq!(from.as_deref(), Some("alerts@example.com"));
+
+        for url in [
+            "mailto://example.com",
+            "mailto://-oQ/tmp@example.com",
+            "mailto://ops@example.com?cc=boss@example.com",
+            "mailto://ops@example.com?from=a@b.com\nBcc: x@y.com",
+            "mailto://Ops <ops@example.com>",
+            "mailtos://ops@example.com",
+        ] {
+            assert!(channel_from_url(url).is_err(), "{}", url);
+        }
+    }
+}
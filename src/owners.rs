@@ -0,0 +1,98 @@
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::{Connection, params};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::notify::{self, Alert};
+use crate::{Config, TOKEN_COLUMNS, Token};
+
+// Sends the owner summaries from the daemon every OWNER_SUMMARY_INTERVAL_DAYS
+pub fn scheduled(conn: &Connection, config: &Config) -> Result<(), Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+    if let Some(last) = crate::get_meta(conn, "last_owner_summary")? {
+        let last = NaiveDateTime::parse_from_str(&last, "%Y-%m-%d %H:%M:%S")?;
+        if (now - last).num_days() < config.owner_summary_interval_days {
+            return Ok(());
+        }
+    }
+    let sent = send(conn, config, false)?;
+    println!("Sent {} owner summary(s)", sent);
+    crate::set_meta(
+        conn,
+        "last_owner_summary",
+        &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )?;
+    Ok(())
+}
+
+// Sends each owner with channels in OWNER_<KEY>_URLS a list of only their items that
+// expire within OWNER_SUMMARY_WITHIN_DAYS, or prints the summaries on a dry run. Owners
+// with nothing due get no message. Returns the number of summaries.
+pub fn send(conn: &Connection, config: &Config, dry_run: bool) -> Result<usize, Box<dyn Error>> {
+    let until = (Utc::now().date_naive()
+        + chrono::Duration::days(config.owner_summary_within_days))
+    .format("%Y-%m-%d")
+    .to_string();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, owner FROM tokens
+         WHERE owner IS NOT NULL AND expires_at <= ?1 AND deleted_at IS NULL
+         ORDER BY expires_at",
+        TOKEN_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map(params![until], |row| {
+            Ok((crate::token_from_row(row)?, row.get::<_, String>(6)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Owners written as "@alice" and "alice" share a summary
+    let mut by_owner: BTreeMap<String, (String, Vec<Token>)> = BTreeMap::new();
+    for (token, owner) in items {
+        by_owner
+            .entry(notify::owner_env_key(&owner))
+            .or_insert_with(|| (owner, Vec::new()))
+            .1
+            .push(token);
+    }
+
+    let mut sent = 0;
+    for (key, (owner, tokens)) in by_owner {
+        let Some(channels) = config.owner_channels.get(&key) else {
+            continue;
+        };
+        let message = summary(config, &owner, &tokens)?;
+        if dry_run {
+            println!("{} ({}):\n{}\n", owner, format_channels(channels), message);
+        } else if let Err(e) = notify::send(channels, &Alert::text(message)) {
+            eprintln!("Failed to send the summary of {}: {}", owner, e);
+            continue;
+        }
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+fn summary(config: &Config, owner: &str, tokens: &[Token]) -> Result<String, Box<dyn Error>> {
+    let mut message = format!(
+        "📋 {}, your items expiring in the next {} days:",
+        owner, config.owner_summary_within_days
+    );
+    for token in tokens {
+        message.push_str(&format!(
+            "\n• {} '{}': {} ({})",
+            token.kind.label(),
+            token.name,
+            token.expires_at,
+            crate::expiry_status(crate::days_until(&token.expires_at)?)
+        ));
+        if token.tenant != crate::DEFAULT_TENANT {
+            message.push_str(&format!(" [{}]", token.tenant));
+        }
+    }
+    Ok(message)
+}
+
+fn format_channels(channels: &[notify::Channel]) -> String {
+    let names: Vec<_> = channels.iter().map(|channel| channel.name()).collect();
+    names.join(", ")
+}
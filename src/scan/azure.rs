@@ -0,0 +1,120 @@
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+
+use super::{Discovered, date_from_rfc3339};
+
+const GRAPH_SCOPE: &str = "https://graph.microsoft.com/.default";
+const GRAPH_APPLICATIONS_URL: &str = "https://graph.microsoft.com/v1.0/applications?$select=displayName,passwordCredentials,keyCredentials";
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct ApplicationPage {
+    value: Vec<Application>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Application {
+    display_name: Option<String>,
+    #[serde(default)]
+    password_credentials: Vec<Credential>,
+    #[serde(default)]
+    key_credentials: Vec<Credential>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Credential {
+    key_id: String,
+    custom_key_identifier: Option<String>, // Certificate thumbprint
+    display_name: Option<String>,
+    end_date_time: Option<String>,
+}
+
+// Lists client secrets and certificates of every app registration in the tenant.
+// The service principal needs the Application.Read.All application permission.
+pub fn scan() -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let tenant_id =
+        env::var("AZURE_TENANT_ID").map_err(|_| "AZURE_TENANT_ID environment variable not set")?;
+    let client_id =
+        env::var("AZURE_CLIENT_ID").map_err(|_| "AZURE_CLIENT_ID environment variable not set")?;
+    let client_secret = env::var("AZURE_CLIENT_SECRET")
+        .map_err(|_| "AZURE_CLIENT_SECRET environment variable not set")?;
+
+    let client = crate::http::client()?;
+    let token: AccessToken = client
+        .post(format!(
+            "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+            tenant_id
+        ))
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("scope", GRAPH_SCOPE),
+            ("grant_type", "client_credentials"),
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    let mut discovered = Vec::new();
+    let mut next_url = Some(GRAPH_APPLICATIONS_URL.to_string());
+
+    while let Some(url) = next_url {
+        let page: ApplicationPage = client
+            .get(&url)
+            .bearer_auth(&token.access_token)
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        for app in page.value {
+            let app_name = app.display_name.unwrap_or_else(|| "unnamed".to_string());
+            for (kind, credentials) in [
+                ("secret", app.password_credentials),
+                ("cert", app.key_credentials),
+            ] {
+                for credential in credentials {
+                    if let Some(token) = to_discovered(&app_name, kind, credential)? {
+                        discovered.push(token);
+                    }
+                }
+            }
+        }
+
+        next_url = page.next_link;
+    }
+
+    Ok(discovered)
+}
+
+fn to_discovered(
+    app_name: &str,
+    kind: &str,
+    credential: Credential,
+) -> Result<Option<Discovered>, Box<dyn Error>> {
+    let Some(end_date_time) = credential.end_date_time else {
+        return Ok(None);
+    };
+    let expires_at = date_from_rfc3339(&end_date_time)?;
+    let label = credential.display_name.unwrap_or(credential.key_id);
+    let fingerprint = if kind == "cert" {
+        credential.custom_key_identifier
+    } else {
+        None
+    };
+
+    Ok(Some(Discovered {
+        name: format!("azure:{}:{}:{}", app_name, kind, label),
+        expires_at,
+        fingerprint,
+        ..Default::default()
+    }))
+}
@@ -0,0 +1,97 @@
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+
+use super::{Discovered, date_from_rfc3339};
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const EXPIRATION_HEADER: &str = "github-authentication-token-expiration";
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct OrgToken {
+    id: u64,
+    token_name: Option<String>,
+    token_expires_at: Option<String>,
+    owner: User,
+}
+
+// Registers the expiry of the token used for scanning and, when an organization is
+// given, of every fine-grained PAT granted access to it (requires org admin).
+// GitHub App installation tokens live for one hour and are not worth tracking.
+pub fn scan(org: Option<&str>) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let token =
+        env::var("GITHUB_TOKEN").map_err(|_| "GITHUB_TOKEN environment variable not set")?;
+    let api_url = env::var("GITHUB_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+    let client = crate::http::client()?;
+
+    let mut discovered = Vec::new();
+
+    let response = request(&client, &token, &format!("{}/user", api_url))
+        .send()?
+        .error_for_status()?;
+    // "2025-06-01 12:00:00 UTC", absent for tokens without an expiry
+    let expiration = response
+        .headers()
+        .get(EXPIRATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_whitespace().next())
+        .map(str::to_string);
+    let user: User = response.json()?;
+    if let Some(expires_at) = expiration {
+        discovered.push(Discovered {
+            name: format!("github:{}:token", user.login),
+            expires_at,
+            ..Default::default()
+        });
+    }
+
+    if let Some(org) = org {
+        for page in 1.. {
+            let tokens: Vec<OrgToken> = request(
+                &client,
+                &token,
+                &format!("{}/orgs/{}/personal-access-tokens", api_url, org),
+            )
+            .query(&[("per_page", PAGE_SIZE), ("page", page)])
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+            let last_page = tokens.len() < PAGE_SIZE;
+            for org_token in tokens {
+                let Some(expires_at) = org_token.token_expires_at else {
+                    continue;
+                };
+                let label = org_token
+                    .token_name
+                    .unwrap_or_else(|| org_token.id.to_string());
+                discovered.push(Discovered {
+                    name: format!("github:{}:{}:{}", org, org_token.owner.login, label),
+                    expires_at: date_from_rfc3339(&expires_at)?,
+                    ..Default::default()
+                });
+            }
+            if last_page {
+                break;
+            }
+        }
+    }
+
+    Ok(discovered)
+}
+
+fn request(client: &Client, token: &str, url: &str) -> RequestBuilder {
+    client
+        .get(url)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", "expiration-notifier")
+}
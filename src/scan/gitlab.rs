@@ -0,0 +1,102 @@
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+
+use super::Discovered;
+
+const DEFAULT_URL: &str = "https://gitlab.com";
+const PAGE_SIZE: &str = "100";
+
+#[derive(Deserialize)]
+struct AccessToken {
+    name: String,
+    active: bool,
+    revoked: bool,
+    expires_at: Option<String>, // YYYY-MM-DD
+    user_id: Option<u64>,
+}
+
+// Imports personal access tokens visible to the scanning token (all users' tokens for
+// admins) plus project and group access tokens of the given projects and groups.
+pub fn scan(projects: &[String], groups: &[String]) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let token =
+        env::var("GITLAB_TOKEN").map_err(|_| "GITLAB_TOKEN environment variable not set")?;
+    let base_url = env::var("GITLAB_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+    let api_url = format!("{}/api/v4", base_url.trim_end_matches('/'));
+    let client = crate::http::client()?;
+
+    let mut discovered = Vec::new();
+
+    let url = format!("{}/personal_access_tokens", api_url);
+    for pat in list(&client, &token, &url)? {
+        let owner = pat.user_id.map(|id| id.to_string()).unwrap_or_default();
+        push_active(&mut discovered, format!("gitlab:user:{}", owner), pat);
+    }
+    for project in projects {
+        let url = format!(
+            "{}/projects/{}/access_tokens",
+            api_url,
+            project.replace('/', "%2F")
+        );
+        for access_token in list(&client, &token, &url)? {
+            let prefix = format!("gitlab:project:{}", project);
+            push_active(&mut discovered, prefix, access_token);
+        }
+    }
+    for group in groups {
+        let url = format!(
+            "{}/groups/{}/access_tokens",
+            api_url,
+            group.replace('/', "%2F")
+        );
+        for access_token in list(&client, &token, &url)? {
+            let prefix = format!("gitlab:group:{}", group);
+            push_active(&mut discovered, prefix, access_token);
+        }
+    }
+
+    Ok(discovered)
+}
+
+fn list(client: &Client, token: &str, url: &str) -> Result<Vec<AccessToken>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    let mut page = "1".to_string();
+
+    loop {
+        let response = client
+            .get(url)
+            .header("PRIVATE-TOKEN", token)
+            .query(&[("per_page", PAGE_SIZE), ("page", page.as_str())])
+            .send()?
+            .error_for_status()?;
+        // Empty on the last page
+        let next_page = response
+            .headers()
+            .get("x-next-page")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        tokens.extend(response.json::<Vec<AccessToken>>()?);
+
+        if next_page.is_empty() {
+            break;
+        }
+        page = next_page;
+    }
+
+    Ok(tokens)
+}
+
+fn push_active(discovered: &mut Vec<Discovered>, prefix: String, token: AccessToken) {
+    if !token.active || token.revoked {
+        return;
+    }
+    if let Some(expires_at) = token.expires_at {
+        discovered.push(Discovered {
+            name: format!("{}:{}", prefix, token.name),
+            expires_at,
+            ..Default::default()
+        });
+    }
+}
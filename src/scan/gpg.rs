@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use super::{Discovered, command_output, date_from_timestamp};
+
+struct Key {
+    key_id: String,
+    expires: Option<i64>,
+    user_id: Option<String>,
+    subkeys: Vec<(String, Option<i64>)>,
+}
+
+// Tracks primary keys and subkeys that have an expiration date, either from the local
+// keyring or from an exported key file. Revoked keys are skipped.
+pub fn scan(file: Option<&Path>) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let mut gpg = Command::new("gpg");
+    gpg.args(["--batch", "--with-colons", "--fixed-list-mode"]);
+    match file {
+        Some(file) => gpg
+            .args(["--import-options", "show-only", "--import"])
+            .arg(file),
+        None => gpg.arg("--list-keys"),
+    };
+    let listing = command_output(&mut gpg)?;
+
+    let mut discovered = Vec::new();
+    for key in parse_keys(&listing) {
+        let user_id = key.user_id.unwrap_or_else(|| key.key_id.clone());
+        if let Some(expires) = key.expires {
+            discovered.push(Discovered {
+                name: format!("gpg:{}:{}", user_id, key.key_id),
+                expires_at: date_from_timestamp(expires)?,
+                ..Default::default()
+            });
+        }
+        for (key_id, expires) in key.subkeys {
+            if let Some(expires) = expires {
+                discovered.push(Discovered {
+                    name: format!("gpg:{}:sub:{}", user_id, key_id),
+                    expires_at: date_from_timestamp(expires)?,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    Ok(discovered)
+}
+
+// Parses `gpg --with-colons` output: pub/sub records carry the validity (field 2),
+// key ID (field 5) and expiration (field 7), uid records the user ID (field 10)
+fn parse_keys(listing: &str) -> Vec<Key> {
+    let mut keys: Vec<Key> = Vec::new();
+    // uid and sub records belong to the preceding pub record
+    let mut in_revoked_key = false;
+
+    for line in listing.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let revoked = fields[1] == "r";
+        let expires = fields[6].parse().ok();
+
+        match fields[0] {
+            "pub" => {
+                in_revoked_key = revoked;
+                if !revoked {
+                    keys.push(Key {
+                        key_id: fields[4].to_string(),
+                        expires,
+                        user_id: None,
+                        subkeys: Vec::new(),
+                    });
+                }
+            }
+            _ if in_revoked_key => {}
+            "uid" => {
+                if let Some(key) = keys.last_mut().filter(|key| key.user_id.is_none()) {
+                    key.user_id = Some(fields[9].to_string());
+                }
+            }
+            "sub" if !revoked => {
+                if let Some(key) = keys.last_mut() {
+                    key.subkeys.push((fields[4].to_string(), expires));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_keys;
+
+    // `gpg --batch --with-colons --fixed-list-mode --list-keys` of a keyring holding a key
+    // expiring 2030-01-01 with a subkey expiring 2029-06-30, a key that never expires
+    // with a subkey expiring 2031-01-01, and a revoked key
+    const LISTING: &str = include_str!("testdata/gpg-keys.txt");
+
+    #[test]
+    fn parses_keys_subkeys_and_user_ids() {
+        let keys = parse_keys(LISTING);
+        assert_eq!(keys.len(), 2);
+
+        assert_eq!(keys[0].key_id, "09E4E9C0BFDCB457");
+        assert_eq!(keys[0].expires, Some(1_893_499_200));
+        assert_eq!(
+            keys[0].user_id.as_deref(),
+            Some("Release Signing <release@example.com>")
+        );
+        assert_eq!(
+            keys[0].subkeys,
+            [("23516BEFFCEC47E9".to_string(), Some(1_877_515_200))]
+        );
+
+        assert_eq!(keys[1].key_id, "CEB81657DB431401");
+        assert_eq!(keys[1].expires, None);
+        assert_eq!(
+            keys[1].user_id.as_deref(),
+            Some("Backup <backup@example.com>")
+        );
+        assert_eq!(
+            keys[1].subkeys,
+            [("A0F2B986E8BCE74B".to_string(), Some(1_925_035_200))]
+        );
+    }
+
+    #[test]
+    fn skips_revoked_keys_with_their_records() {
+        let listing = "pub:r:255:22:0DF7F16AE1A8DD8A:1792112891:1830340800::-:::sc:::::ed25519:::0:
+uid:r::::1792112891::5626D54278E39514CD5BEEE0F156438F0AC8F715::Old Key <old@example.com>::::::::::0:
+sub:u:255:18:23516BEFFCEC47E9:1792112891:1877515200:::::e:::::cv25519::";
+        assert!(parse_keys(listing).is_empty());
+    }
+
+    #[test]
+    fn ignores_short_and_unrelated_records() {
+        assert!(parse_keys("").is_empty());
+        assert!(parse_keys("tru::1:1792112896:1830340800:3:1:5\nnot gpg output").is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::NaiveDate;
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use super::{Discovered, command_output};
+
+const STOREPASS_VAR: &str = "KEYTOOL_STOREPASS";
+
+// Lists every certificate in a JKS or PKCS12 keystore with `keytool -list -v`.
+// The password comes from --password or KEYSTORE_PASSWORD and is handed to keytool
+// through its environment rather than the command line.
+pub fn scan(file: &Path, password: Option<&str>) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let password = password
+        .map(str::to_string)
+        .or_else(|| env::var("KEYSTORE_PASSWORD").ok());
+
+    let mut keytool = Command::new("keytool");
+    keytool
+        .args(["-list", "-v", "-keystore"])
+        .arg(file)
+        .env("LC_ALL", "C");
+    if let Some(password) = password {
+        keytool
+            .args(["-storepass:env", STOREPASS_VAR])
+            .env(STOREPASS_VAR, password);
+    }
+    let listing = command_output(&mut keytool)?;
+
+    let keystore = file.file_stem().unwrap_or_default().to_string_lossy();
+    parse_listing(&keystore, &listing)
+}
+
+// Parses `keytool -list -v` output: every certificate of every alias, with the issuer
+// and SHA-256 fingerprint listed around its validity
+fn parse_listing(keystore: &str, listing: &str) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let mut discovered: Vec<Discovered> = Vec::new();
+    let mut alias = String::new();
+    let mut chain_index = 1;
+    let mut issuer = None;
+
+    for line in listing.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("Alias name:") {
+            alias = name.trim().to_string();
+            chain_index = 1;
+        } else if let Some(index) = line
+            .strip_prefix("Certificate[")
+            .and_then(|rest| rest.strip_suffix("]:"))
+        {
+            chain_index = index.parse()?;
+        } else if let Some(name) = line.strip_prefix("Issuer:") {
+            issuer = Some(name.trim().to_string());
+        } else if let Some(fingerprint) = line.strip_prefix("SHA256:") {
+            // Listed after the validity of the certificate it belongs to
+            if let Some(certificate) = discovered.last_mut() {
+                certificate.fingerprint = Some(fingerprint.trim().to_string());
+            }
+        } else if let Some((_, until)) = line.split_once("until:") {
+            // Leaf certificates keep the alias as name, CA certificates get the chain index
+            let name = if chain_index == 1 {
+                format!("keystore:{}:{}", keystore, alias)
+            } else {
+                format!("keystore:{}:{}#{}", keystore, alias, chain_index)
+            };
+            discovered.push(Discovered {
+                name,
+                expires_at: parse_keytool_date(until.trim())?,
+                issuer: issuer.take(),
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(discovered)
+}
+
+// "Tue Dec 31 23:59:59 UTC 2024": chrono cannot parse zone abbreviations, so only
+// the month, day and year are used
+fn parse_keytool_date(date: &str) -> Result<String, Box<dyn Error>> {
+    let parts: Vec<&str> = date.split_whitespace().collect();
+    if parts.len() != 6 {
+        return Err(format!("Unexpected keytool date: {}", date).into());
+    }
+    let date = NaiveDate::parse_from_str(
+        &format!("{} {} {}", parts[1], parts[2], parts[5]),
+        "%b %d %Y",
+    )?;
+    Ok(date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `LC_ALL=C keytool -list -v` of a PKCS12 keystore holding a trusted root valid until
+    // 2034-01-01 and a key whose chain is a leaf valid until 2025-12-31 and that root
+    const LISTING: &str = include_str!("testdata/keytool-list.txt");
+
+    const ROOT_FINGERPRINT: &str = "BE:8C:79:68:3B:B0:73:21:59:1B:D1:95:6F:A5:E5:2D:9B:3F:F4:F0:60:9A:09:2F:FE:7D:59:41:13:85:B0:E6";
+
+    #[test]
+    fn parses_every_certificate_of_every_alias() {
+        let certificates = parse_listing("store", LISTING).unwrap();
+        let summary: Vec<_> = certificates
+            .iter()
+            .map(|certificate| {
+                (
+                    certificate.name.as_str(),
+                    certificate.expires_at.as_str(),
+                    certificate.issuer.as_deref(),
+                    certificate.fingerprint.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "keystore:store:root",
+                    "2034-01-01",
+                    Some("CN=Example Root CA"),
+                    Some(ROOT_FINGERPRINT),
+                ),
+                (
+                    "keystore:store:server",
+                    "2025-12-31",
+                    Some("CN=Example Root CA"),
+                    Some(
+                        "F9:53:F4:12:54:87:F1:DB:DC:85:23:E1:7C:EC:AE:F3:26:98:85:D1:9E:38:DB:B9:2B:14:BA:5A:1A:9F:4E:EB"
+                    ),
+                ),
+                (
+                    "keystore:store:server#2",
+                    "2034-01-01",
+                    Some("CN=Example Root CA"),
+                    Some(ROOT_FINGERPRINT),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_an_empty_keystore() {
+        let listing =
+            "Keystore type: PKCS12\nKeystore provider: SUN\n\nYour keystore contains 0 entries\n";
+        assert!(parse_listing("store", listing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_keytool_dates() {
+        assert_eq!(
+            parse_keytool_date("Tue Dec 31 23:59:59 UTC 2024").unwrap(),
+            "2024-12-31"
+        );
+        assert!(parse_keytool_date("Tue Dec 31 2024").is_err());
+        assert!(parse_keytool_date("Tue Foo 31 23:59:59 UTC 2024").is_err());
+        assert!(parse_keytool_date("").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_listings() {
+        assert!(parse_listing("store", "Certificate[x]:").is_err());
+        assert!(parse_listing("store", "Valid from: today until: tomorrow").is_err());
+    }
+}
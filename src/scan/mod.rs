@@ -0,0 +1,663 @@
+use clap::Subcommand;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+#[cfg(any(
+    feature = "scan-gpg",
+    feature = "scan-ssh-cert",
+    feature = "scan-keystore"
+))]
+use std::path::{self, Path, PathBuf};
+
+use crate::Kind;
+use crate::hooks::Hooks;
+
+// Each scanner is behind its own scan-* feature
+#[cfg(feature = "scan-azure")]
+mod azure;
+#[cfg(feature = "scan-github")]
+mod github;
+#[cfg(feature = "scan-gitlab")]
+mod gitlab;
+#[cfg(feature = "scan-gpg")]
+mod gpg;
+#[cfg(feature = "scan-keystore")]
+mod keystore;
+#[cfg(feature = "scan-password-managers")]
+mod password_manager;
+#[cfg(feature = "scan-prometheus")]
+mod prometheus;
+#[cfg(feature = "scan-ssh-cert")]
+mod ssh_cert;
+
+// Token discovered by a scanner
+#[derive(Debug, Default)]
+pub struct Discovered {
+    pub name: String,
+    pub expires_at: String,          // YYYY-MM-DD
+    pub fingerprint: Option<String>, // Certificates only
+    pub issuer: Option<String>,
+    // Defaults to certificate for items with a fingerprint, token otherwise
+    pub kind: Option<Kind>,
+}
+
+// Scan sources, stored as JSON on imported tokens so the daemon can re-scan them
+#[derive(Subcommand, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ScanSource {
+    /// Import Azure AD app registration secrets and certificates via Microsoft Graph
+    #[cfg(feature = "scan-azure")]
+    Azure,
+    /// Import GitHub personal access token expirations
+    #[cfg(feature = "scan-github")]
+    Github {
+        /// Also import fine-grained PATs with access to this organization
+        #[arg(long)]
+        org: Option<String>,
+    },
+    /// Import GitLab personal, project and group access token expirations
+    #[cfg(feature = "scan-gitlab")]
+    Gitlab {
+        /// Project ID or path whose project access tokens to import (repeatable)
+        #[arg(long = "project")]
+        projects: Vec<String>,
+        /// Group ID or path whose group access tokens to import (repeatable)
+        #[arg(long = "group")]
+        groups: Vec<String>,
+    },
+    /// Import GPG primary key and subkey expirations
+    #[cfg(feature = "scan-gpg")]
+    Gpg {
+        /// Exported key file to read instead of the local keyring
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Import OpenSSH certificate valid-before times
+    #[cfg(feature = "scan-ssh-cert")]
+    SshCert {
+        /// Certificate file or directory containing `*-cert.pub` files
+        #[arg(default_value = "~/.ssh")]
+        path: PathBuf,
+    },
+    /// Import certificate expirations from a JKS or PKCS12 keystore
+    #[cfg(feature = "scan-keystore")]
+    Keystore {
+        file: PathBuf,
+        /// Keystore password (defaults to KEYSTORE_PASSWORD, which re-scans always use)
+        #[arg(long)]
+        #[serde(skip)]
+        password: Option<String>,
+    },
+    /// Import 1Password items with an expiry field using the `op` CLI
+    #[cfg(feature = "scan-password-managers")]
+    #[command(name = "1password")]
+    #[serde(rename = "1password")]
+    OnePassword {
+        /// Only scan this vault
+        #[arg(long)]
+        vault: Option<String>,
+    },
+    /// Import Bitwarden items with an expiry field or card expiration using the `bw` CLI
+    #[cfg(feature = "scan-password-managers")]
+    Bitwarden,
+    /// Import expiry timestamps from a Prometheus query, e.g. blackbox exporter probes
+    #[cfg(feature = "scan-prometheus")]
+    Prometheus {
+        /// Prometheus base URL, e.g. http://prometheus:9090
+        url: String,
+        /// PromQL query whose values are expiry Unix timestamps
+        #[arg(long, default_value = "probe_ssl_earliest_cert_expiry")]
+        query: String,
+        /// Label whose value names each item
+        #[arg(long, default_value = "instance")]
+        label: String,
+        #[arg(long, value_enum, default_value_t = Kind::Certificate)]
+        kind: Kind,
+    },
+}
+
+impl ScanSource {
+    // Matches on *self so that builds without any scanner get an empty match
+    fn scan(&self) -> Result<Vec<Discovered>, Box<dyn Error>> {
+        match *self {
+            #[cfg(feature = "scan-azure")]
+            ScanSource::Azure => azure::scan(),
+            #[cfg(feature = "scan-github")]
+            ScanSource::Github { ref org } => github::scan(org.as_deref()),
+            #[cfg(feature = "scan-gitlab")]
+            ScanSource::Gitlab {
+                ref projects,
+                ref groups,
+            } => gitlab::scan(projects, groups),
+            #[cfg(feature = "scan-gpg")]
+            ScanSource::Gpg { ref file } => gpg::scan(file.as_deref()),
+            #[cfg(feature = "scan-ssh-cert")]
+            ScanSource::SshCert { ref path } => ssh_cert::scan(path),
+            #[cfg(feature = "scan-keystore")]
+            ScanSource::Keystore {
+                ref file,
+                ref password,
+            } => keystore::scan(file, password.as_deref()),
+            #[cfg(feature = "scan-password-managers")]
+            ScanSource::OnePassword { ref vault } => {
+                password_manager::scan_1password(vault.as_deref())
+            }
+            #[cfg(feature = "scan-password-managers")]
+            ScanSource::Bitwarden => password_manager::scan_bitwarden(),
+            #[cfg(feature = "scan-prometheus")]
+            ScanSource::Prometheus {
+                ref url,
+                ref query,
+                ref label,
+                kind,
+            } => prometheus::scan(url, query, label, kind),
+        }
+    }
+
+    // Re-scans run from the daemon's working directory, so paths are stored absolute.
+    // The catch-all arm is unreachable in builds with only path-based scanners.
+    #[allow(unreachable_patterns)]
+    fn with_absolute_paths(mut self) -> Result<Self, Box<dyn Error>> {
+        match &mut self {
+            #[cfg(feature = "scan-gpg")]
+            ScanSource::Gpg { file: Some(file) } => *file = path::absolute(expand_home(file))?,
+            #[cfg(feature = "scan-ssh-cert")]
+            ScanSource::SshCert { path: file } => *file = path::absolute(expand_home(file))?,
+            #[cfg(feature = "scan-keystore")]
+            ScanSource::Keystore { file, .. } => *file = path::absolute(expand_home(file))?,
+            _ => {}
+        }
+        Ok(self)
+    }
+}
+
+pub fn run(
+    conn: &Connection,
+    tenant: &str,
+    source: ScanSource,
+    hooks: &Hooks,
+) -> Result<(), Box<dyn Error>> {
+    let source = source.with_absolute_paths()?;
+    let discovered = source.scan()?;
+    let changes = register(conn, tenant, &source, &discovered, hooks)?;
+
+    for token in &discovered {
+        println!("{:<50} {}", token.name, token.expires_at);
+    }
+    eprintln!("Imported {} token(s)", discovered.len());
+    for change in changes {
+        eprintln!("Warning: {}", change);
+    }
+
+    Ok(())
+}
+
+// Re-runs every scan that tracked tokens were imported from, so renewed credentials
+// get their new expiry instead of alerting on the old one. Returns alerts for
+// certificates that changed unexpectedly, with the tenant they belong to.
+pub fn rescan_all(conn: &Connection, hooks: &Hooks) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT tenant, source FROM tokens
+             WHERE source IS NOT NULL AND deleted_at IS NULL",
+    )?;
+    let sources = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut alerts = Vec::new();
+    for (tenant, descriptor) in sources {
+        match rescan(conn, &tenant, &descriptor, hooks) {
+            Ok((count, changes)) => {
+                println!(
+                    "Re-scanned {} for {}: {} token(s)",
+                    descriptor, tenant, count
+                );
+                alerts.extend(changes.into_iter().map(|change| (tenant.clone(), change)));
+            }
+            Err(e) => eprintln!("Failed to re-scan {} for {}: {}", descriptor, tenant, e),
+        }
+    }
+
+    Ok(alerts)
+}
+
+fn rescan(
+    conn: &Connection,
+    tenant: &str,
+    descriptor: &str,
+    hooks: &Hooks,
+) -> Result<(usize, Vec<String>), Box<dyn Error>> {
+    let source: ScanSource = serde_json::from_str(descriptor)?;
+    let discovered = source.scan()?;
+    let changes = register(conn, tenant, &source, &discovered, hooks)?;
+    Ok((discovered.len(), changes))
+}
+
+// Keeps last_notified when a known token is re-imported and runs the renewed hook when
+// its expiry moved forward. A new item that duplicates a tracked one is linked to it
+// instead, see find_duplicate. Returns a message for every certificate that was replaced
+// unexpectedly.
+fn register(
+    conn: &Connection,
+    tenant: &str,
+    source: &ScanSource,
+    discovered: &[Discovered],
+    hooks: &Hooks,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let descriptor = serde_json::to_string(source)?;
+    let mut changes = Vec::new();
+    let mut renewals = Vec::new();
+
+    // One transaction per scan, with statements prepared once for all tokens
+    let tx = conn.unchecked_transaction()?;
+    // Trashed tokens are left alone; a token tracked under the scanned name wins over
+    // one linked to it
+    let mut select = tx.prepare(
+        "SELECT name, expires_at, fingerprint, issuer, source FROM tokens
+         WHERE tenant = ?1 AND (name = ?2 OR scanned_as = ?2) AND deleted_at IS NULL
+         ORDER BY name = ?2 DESC, id
+         LIMIT 1",
+    )?;
+    let mut upsert = tx.prepare(
+        "INSERT INTO tokens (tenant, name, expires_at, source, fingerprint, issuer, kind)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(tenant, name) DO UPDATE SET
+             expires_at = excluded.expires_at,
+             source = excluded.source,
+             fingerprint = excluded.fingerprint,
+             issuer = excluded.issuer",
+    )?;
+    let mut manual = ManualCertificates::load(&tx, tenant)?;
+
+    for token in discovered {
+        let kind = token.kind.unwrap_or(if token.fingerprint.is_some() {
+            Kind::Certificate
+        } else {
+            Kind::Token
+        });
+        let lookup = |select: &mut rusqlite::Statement| {
+            select
+                .query_row(params![tenant, token.name], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                })
+                .optional()
+        };
+        let mut previous = lookup(&mut select)?;
+        if previous.is_none()
+            && let Some(name) = find_duplicate(
+                &tx,
+                tenant,
+                &token.name,
+                token.fingerprint.as_deref(),
+                kind,
+                i64::MAX,
+                &mut manual,
+            )?
+        {
+            tx.execute(
+                "UPDATE tokens SET scanned_as = ?1 WHERE tenant = ?2 AND name = ?3",
+                params![token.name, tenant, name],
+            )?;
+            eprintln!("Linked '{}' to tracked token '{}'", token.name, name);
+            previous = lookup(&mut select)?;
+        }
+        // A linked token keeps its name, and the source it was first scanned from
+        let (name, source) = match &previous {
+            Some((name, _, _, _, source)) if *name != token.name => {
+                (name.as_str(), source.as_deref().unwrap_or(&descriptor))
+            }
+            _ => (token.name.as_str(), descriptor.as_str()),
+        };
+
+        if let Some((_, expires_at, Some(fingerprint), issuer, _)) = &previous {
+            changes.extend(unexpected_change(
+                token,
+                expires_at,
+                fingerprint,
+                issuer.as_deref(),
+            ));
+        }
+
+        upsert.execute(params![
+            tenant,
+            name,
+            token.expires_at,
+            source,
+            token.fingerprint,
+            token.issuer,
+            // Only set on first import, so a kind changed with edit --kind sticks
+            kind
+        ])?;
+
+        if let Some((name, expires_at, _, _, _)) = previous {
+            crate::record_expiry_change(&tx, tenant, &name, &expires_at, &token.expires_at)?;
+            if expires_at < token.expires_at {
+                renewals.push((name, token, expires_at));
+            }
+        }
+    }
+    drop((select, upsert));
+    tx.commit()?;
+
+    // Hooks run once the scan is committed
+    for (name, token, previous) in renewals {
+        hooks.renewed(tenant, &name, &previous, &token.expires_at);
+    }
+
+    Ok(changes)
+}
+
+// Tracked token the scanned item duplicates under another name: one with the same
+// certificate fingerprint, or for certificates a manually added token naming the same
+// host. Only tokens older than `before_id` are considered, so `dedupe` keeps the oldest.
+fn find_duplicate(
+    conn: &Connection,
+    tenant: &str,
+    name: &str,
+    fingerprint: Option<&str>,
+    kind: Kind,
+    before_id: i64,
+    manual: &mut ManualCertificates,
+) -> rusqlite::Result<Option<String>> {
+    if let Some(fingerprint) = fingerprint {
+        let duplicate = conn
+            .prepare_cached(
+                "SELECT name FROM tokens
+                 WHERE tenant = ?1 AND name != ?2 AND fingerprint = ?3 AND id < ?4
+                     AND deleted_at IS NULL
+                 ORDER BY id LIMIT 1",
+            )?
+            .query_row(params![tenant, name, fingerprint, before_id], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if duplicate.is_some() {
+            return Ok(duplicate);
+        }
+    }
+
+    Ok(host_of(name)
+        .filter(|_| kind == Kind::Certificate)
+        .and_then(|host| manual.take(&host, before_id)))
+}
+
+// Manually added certificates naming a host, loaded once per scan or dedupe rather than
+// for every scanned item
+struct ManualCertificates(Vec<(i64, String, String)>); // id, name, host
+
+impl ManualCertificates {
+    fn load(conn: &Connection, tenant: &str) -> rusqlite::Result<Self> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name FROM tokens
+             WHERE tenant = ?1 AND source IS NULL AND deleted_at IS NULL
+                 AND kind = 'certificate'
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![tenant], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Self(
+            rows.into_iter()
+                .filter_map(|(id, name)| host_of(&name).map(|host| (id, name, host)))
+                .collect(),
+        ))
+    }
+
+    // Oldest certificate for `host` added before `before_id`. Linking or merging gives it
+    // a source, so it stops being a manual certificate and is removed.
+    fn take(&mut self, host: &str, before_id: i64) -> Option<String> {
+        let index = self
+            .0
+            .iter()
+            .position(|(id, _, manual)| *id < before_id && manual == host)?;
+        Some(self.0.remove(index).1)
+    }
+}
+
+// Host a token name refers to: "prometheus:https://Example.com:443/health",
+// "example.com:443" and "example.com" all give "example.com"
+fn host_of(name: &str) -> Option<String> {
+    let mut host = name.trim();
+    // Scanner prefix, such as prometheus: or keystore:
+    if let Some((prefix, rest)) = host.split_once(':')
+        && !rest.starts_with("//")
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        host = rest;
+    }
+    if let Some((_, rest)) = host.split_once("://") {
+        host = rest;
+    }
+    host = host.split('/').next().unwrap_or(host);
+    if let Some((rest, port)) = host.rsplit_once(':')
+        && port.chars().all(|c| c.is_ascii_digit())
+    {
+        host = rest;
+    }
+
+    let valid = host.contains('.')
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    valid.then(|| host.to_ascii_lowercase())
+}
+
+// Merges scanned tokens into the tracked tokens they duplicate, for overlaps created
+// before scans linked duplicates. The kept token takes the scanned expiry, certificate
+// details and source, so re-scans update it.
+pub fn dedupe(conn: &Connection, tenant: &str, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, fingerprint, kind FROM tokens
+         WHERE tenant = ?1 AND source IS NOT NULL AND deleted_at IS NULL
+         ORDER BY id",
+    )?;
+    let scanned = stmt
+        .query_map(params![tenant], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Kind>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let tx = conn.unchecked_transaction()?;
+    let mut manual = ManualCertificates::load(&tx, tenant)?;
+    let mut merged = 0;
+    for (id, name, fingerprint, kind) in scanned {
+        let Some(target) = find_duplicate(
+            &tx,
+            tenant,
+            &name,
+            fingerprint.as_deref(),
+            kind,
+            id,
+            &mut manual,
+        )?
+        else {
+            continue;
+        };
+        if dry_run {
+            println!("Would merge '{}' into '{}'", name, target);
+        } else {
+            merge(&tx, tenant, &name, &target)?;
+            eprintln!("Merged '{}' into '{}'", name, target);
+        }
+        merged += 1;
+    }
+    tx.commit()?;
+
+    match (merged, dry_run) {
+        (0, _) => eprintln!("No duplicates found"),
+        (_, true) => eprintln!(
+            "{} duplicate(s) found, run without --dry-run to merge",
+            merged
+        ),
+        (_, false) => eprintln!("Merged {} duplicate(s)", merged),
+    }
+    Ok(())
+}
+
+fn merge(conn: &Connection, tenant: &str, from: &str, into: &str) -> rusqlite::Result<()> {
+    let (expires_at, source, fingerprint, issuer): (
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+    ) = conn.query_row(
+        "SELECT expires_at, source, fingerprint, issuer FROM tokens
+             WHERE tenant = ?1 AND name = ?2",
+        params![tenant, from],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let previous: String = conn.query_row(
+        "SELECT expires_at FROM tokens WHERE tenant = ?1 AND name = ?2",
+        params![tenant, into],
+        |row| row.get(0),
+    )?;
+
+    conn.execute(
+        "UPDATE tokens SET expires_at = ?1, source = COALESCE(source, ?2),
+             fingerprint = ?3, issuer = ?4, scanned_as = ?5
+         WHERE tenant = ?6 AND name = ?7",
+        params![expires_at, source, fingerprint, issuer, from, tenant, into],
+    )?;
+    crate::record_expiry_change(conn, tenant, into, &previous, &expires_at)?;
+    conn.execute(
+        "DELETE FROM tokens WHERE tenant = ?1 AND name = ?2",
+        params![tenant, from],
+    )?;
+    conn.execute(
+        "DELETE FROM deliveries WHERE tenant = ?1 AND name = ?2",
+        params![tenant, from],
+    )?;
+    Ok(())
+}
+
+// A new fingerprint is expected when the same issuer renews a certificate. A different
+// issuer, or a replacement that does not extend the expiry, is worth a closer look.
+fn unexpected_change(
+    token: &Discovered,
+    old_expires_at: &str,
+    old_fingerprint: &str,
+    old_issuer: Option<&str>,
+) -> Option<String> {
+    let fingerprint = token.fingerprint.as_deref()?;
+    if fingerprint == old_fingerprint {
+        return None;
+    }
+
+    match (old_issuer, token.issuer.as_deref()) {
+        (Some(old_issuer), Some(issuer)) if old_issuer != issuer => Some(format!(
+            "🔀 Certificate '{}' was replaced by one from a different issuer: {} → {}",
+            token.name, old_issuer, issuer
+        )),
+        _ if token.expires_at.as_str() <= old_expires_at => Some(format!(
+            "🔀 Certificate '{}' was replaced without extending its expiry ({} → {})",
+            token.name, old_expires_at, token.expires_at
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(any(
+    feature = "scan-gpg",
+    feature = "scan-ssh-cert",
+    feature = "scan-keystore"
+))]
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+// Converts a Unix timestamp to a YYYY-MM-DD date
+#[cfg(any(
+    feature = "scan-gpg",
+    feature = "scan-ssh-cert",
+    feature = "scan-password-managers",
+    feature = "scan-prometheus"
+))]
+fn date_from_timestamp(seconds: i64) -> Result<String, Box<dyn Error>> {
+    let date = chrono::DateTime::from_timestamp(seconds, 0).ok_or("Timestamp out of range")?;
+    Ok(date.format("%Y-%m-%d").to_string())
+}
+
+// Converts an RFC 3339 timestamp from an API response to a YYYY-MM-DD date
+#[cfg(any(feature = "scan-azure", feature = "scan-github"))]
+fn date_from_rfc3339(timestamp: &str) -> Result<String, Box<dyn Error>> {
+    Ok(chrono::DateTime::parse_from_rfc3339(timestamp)?
+        .with_timezone(&chrono::Utc)
+        .format("%Y-%m-%d")
+        .to_string())
+}
+
+// Runs an external tool and returns its stdout, failing with its stderr
+#[cfg(any(
+    feature = "scan-gpg",
+    feature = "scan-keystore",
+    feature = "scan-password-managers"
+))]
+fn command_output(command: &mut std::process::Command) -> Result<String, Box<dyn Error>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::host_of;
+
+    #[test]
+    fn host_of_strips_scanner_prefix_scheme_path_and_port() {
+        let host = Some("example.com".to_string());
+        assert_eq!(host_of("example.com"), host);
+        assert_eq!(host_of("example.com:443"), host);
+        assert_eq!(host_of("https://example.com/health"), host);
+        assert_eq!(host_of("prometheus:https://Example.com:443/health"), host);
+        assert_eq!(host_of("keystore:example.com"), host);
+        assert_eq!(host_of("  EXAMPLE.com  "), host);
+    }
+
+    #[test]
+    fn host_of_keeps_subdomains_apart() {
+        assert_eq!(
+            host_of("api.example.com"),
+            Some("api.example.com".to_string())
+        );
+        assert_ne!(host_of("api.example.com"), host_of("example.com"));
+    }
+
+    #[test]
+    fn host_of_rejects_names_that_are_not_hosts() {
+        assert_eq!(host_of("github-token"), None);
+        assert_eq!(host_of("localhost:8080"), None);
+        assert_eq!(host_of("keystore:/etc/ssl/store.jks"), None);
+        assert_eq!(host_of("my token.txt"), None);
+        assert_eq!(host_of(""), None);
+    }
+}
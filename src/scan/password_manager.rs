@@ -0,0 +1,177 @@
+use chrono::{Days, Months, NaiveDate};
+use serde::Deserialize;
+use std::error::Error;
+use std::process::Command;
+
+use super::{Discovered, command_output, date_from_timestamp};
+
+#[derive(Deserialize)]
+struct OpItemSummary {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct OpItem {
+    title: String,
+    #[serde(default)]
+    fields: Vec<OpField>,
+}
+
+#[derive(Deserialize)]
+struct OpField {
+    id: String,
+    label: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BwItem {
+    name: String,
+    fields: Option<Vec<BwField>>,
+    card: Option<BwCard>,
+}
+
+#[derive(Deserialize)]
+struct BwField {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BwCard {
+    exp_month: Option<String>,
+    exp_year: Option<String>,
+}
+
+// Imports 1Password items having a field labelled like "expires"/"expiry date"
+// (including credit card expiry) using a signed-in `op` CLI
+pub fn scan_1password(vault: Option<&str>) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let mut list = Command::new("op");
+    list.args(["item", "list", "--format", "json"]);
+    if let Some(vault) = vault {
+        list.args(["--vault", vault]);
+    }
+    let summaries: Vec<OpItemSummary> = serde_json::from_str(&command_output(&mut list)?)?;
+
+    let mut discovered = Vec::new();
+    for summary in summaries {
+        let mut get = Command::new("op");
+        get.args(["item", "get", &summary.id, "--format", "json"]);
+        let item: OpItem = serde_json::from_str(&command_output(&mut get)?)?;
+
+        let expiry = item.fields.iter().find_map(|field| {
+            let label = field.label.as_deref().unwrap_or(&field.id);
+            if is_expiry_label(label) || field.id == "expiry" {
+                field.value.as_deref().and_then(parse_expiry)
+            } else {
+                None
+            }
+        });
+        if let Some(expires_at) = expiry {
+            discovered.push(Discovered {
+                name: format!("1password:{}", item.title),
+                expires_at,
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(discovered)
+}
+
+// Imports Bitwarden items with an expiry custom field or card expiration using an
+// unlocked `bw` CLI (BW_SESSION must be set)
+pub fn scan_bitwarden() -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let mut list = Command::new("bw");
+    list.args(["list", "items"]);
+    let items: Vec<BwItem> = serde_json::from_str(&command_output(&mut list)?)?;
+
+    let mut discovered = Vec::new();
+    for item in items {
+        let field_expiry = item.fields.iter().flatten().find_map(|field| {
+            if is_expiry_label(field.name.as_deref().unwrap_or_default()) {
+                field.value.as_deref().and_then(parse_expiry)
+            } else {
+                None
+            }
+        });
+        let card_expiry = item.card.and_then(|card| {
+            let month = card.exp_month?.parse().ok()?;
+            let year = card.exp_year?.parse().ok()?;
+            end_of_month(year, month)
+        });
+
+        if let Some(expires_at) = field_expiry.or(card_expiry) {
+            discovered.push(Discovered {
+                name: format!("bitwarden:{}", item.name),
+                expires_at,
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(discovered)
+}
+
+fn is_expiry_label(label: &str) -> bool {
+    let label = label.to_lowercase();
+    label.contains("expir") || label.contains("valid until")
+}
+
+// Accepts YYYY-MM-DD, Unix timestamps (1Password date fields), YYYYMM (1Password
+// month-year fields) and MM/YYYY or MM/YY
+fn parse_expiry(value: &str) -> Option<String> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date.format("%Y-%m-%d").to_string());
+    }
+    if value.len() == 6 && value.chars().all(|c| c.is_ascii_digit()) {
+        return end_of_month(value[..4].parse().ok()?, value[4..].parse().ok()?);
+    }
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return date_from_timestamp(timestamp).ok();
+    }
+    if let Some((month, year)) = value.split_once('/') {
+        let year: i32 = year.parse().ok()?;
+        let year = if year < 100 { 2000 + year } else { year };
+        return end_of_month(year, month.parse().ok()?);
+    }
+    None
+}
+
+// Card-style expirations are valid through the last day of the month
+fn end_of_month(year: i32, month: u32) -> Option<String> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let last = first.checked_add_months(Months::new(1))? - Days::new(1);
+    Some(last.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_expiry;
+
+    #[test]
+    fn parses_dates_and_timestamps() {
+        assert_eq!(parse_expiry("2030-01-15").as_deref(), Some("2030-01-15"));
+        assert_eq!(parse_expiry(" 2030-01-15\n").as_deref(), Some("2030-01-15"));
+        assert_eq!(parse_expiry("1893456000").as_deref(), Some("2030-01-01"));
+    }
+
+    #[test]
+    fn month_year_fields_last_through_the_month() {
+        assert_eq!(parse_expiry("203002").as_deref(), Some("2030-02-28"));
+        assert_eq!(parse_expiry("202802").as_deref(), Some("2028-02-29"));
+        assert_eq!(parse_expiry("12/2030").as_deref(), Some("2030-12-31"));
+        assert_eq!(parse_expiry("02/28").as_deref(), Some("2028-02-29"));
+    }
+
+    #[test]
+    fn rejects_other_values() {
+        assert_eq!(parse_expiry(""), None);
+        assert_eq!(parse_expiry("soon"), None);
+        assert_eq!(parse_expiry("203013"), None);
+        assert_eq!(parse_expiry("13/2030"), None);
+        assert_eq!(parse_expiry("2030-02-30"), None);
+    }
+}
@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+
+use super::{Discovered, date_from_timestamp};
+use crate::Kind;
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    data: QueryData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryData {
+    result_type: String,
+    result: Vec<Sample>,
+}
+
+// Instant vector element; the value is [evaluation time, "sample value"]
+#[derive(Deserialize)]
+struct Sample {
+    metric: HashMap<String, String>,
+    value: (f64, String),
+}
+
+// Runs an instant query whose samples are expiry Unix timestamps, such as the blackbox
+// exporter's probe_ssl_earliest_cert_expiry, and names each item after the given label.
+// PROMETHEUS_TOKEN is sent as a bearer token when set.
+pub fn scan(
+    url: &str,
+    query: &str,
+    label: &str,
+    kind: Kind,
+) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let client = crate::http::client()?;
+    let mut request = client
+        .get(format!("{}/api/v1/query", url.trim_end_matches('/')))
+        .query(&[("query", query)]);
+    if let Ok(token) = env::var("PROMETHEUS_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let response: QueryResponse = request.send()?.error_for_status()?.json()?;
+    if response.data.result_type != "vector" {
+        return Err(format!(
+            "Query returned a {}, expected an instant vector",
+            response.data.result_type
+        )
+        .into());
+    }
+
+    // Series sharing the label value (e.g. one per probing job) keep the earliest expiry
+    let mut earliest: HashMap<String, i64> = HashMap::new();
+    for sample in response.data.result {
+        let name = sample.metric.get(label).cloned().ok_or_else(|| {
+            format!(
+                "Series {:?} has no '{}' label, see --label",
+                sample.metric, label
+            )
+        })?;
+        let expires_at = sample
+            .value
+            .1
+            .parse::<f64>()
+            .map_err(|_| format!("Value of '{}' is not a timestamp", name))?;
+        // Exporters report NaN or +Inf when a probe fails; `as i64` would turn NaN into
+        // 1970-01-01
+        if !expires_at.is_finite() {
+            eprintln!(
+                "Warning: skipping '{}', its value {} is not a timestamp",
+                name, sample.value.1
+            );
+            continue;
+        }
+        let expires_at = expires_at as i64;
+        earliest
+            .entry(name)
+            .and_modify(|current| *current = (*current).min(expires_at))
+            .or_insert(expires_at);
+    }
+
+    let mut discovered = Vec::new();
+    for (name, expires_at) in earliest {
+        discovered.push(Discovered {
+            name: format!("prometheus:{}", name),
+            expires_at: date_from_timestamp(expires_at)?,
+            kind: Some(kind),
+            ..Default::default()
+        });
+    }
+    Ok(discovered)
+}
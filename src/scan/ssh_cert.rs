@@ -0,0 +1,205 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::{Discovered, date_from_timestamp};
+
+const CERT_SUFFIX: &str = "-cert.pub";
+
+// Imports the valid-before time of an OpenSSH certificate, or of every `*-cert.pub`
+// file in a directory. Certificates valid forever are skipped.
+pub fn scan(path: &Path) -> Result<Vec<Discovered>, Box<dyn Error>> {
+    let files = if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.to_string_lossy().ends_with(CERT_SUFFIX) {
+                files.push(file);
+            }
+        }
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut discovered = Vec::new();
+    for file in files {
+        let certificate = parse_certificate(&fs::read_to_string(&file)?)
+            .map_err(|e| format!("{}: {}", file.display(), e))?;
+        if certificate.valid_before == u64::MAX {
+            continue;
+        }
+        let key_id = if certificate.key_id.is_empty() {
+            file.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            certificate.key_id
+        };
+        discovered.push(Discovered {
+            name: format!("ssh-cert:{}", key_id),
+            expires_at: date_from_timestamp(certificate.valid_before.try_into()?)?,
+            ..Default::default()
+        });
+    }
+
+    Ok(discovered)
+}
+
+struct Certificate {
+    key_id: String,
+    valid_before: u64,
+}
+
+// Parses the fields of the certificate blob (see PROTOCOL.certkeys in OpenSSH)
+// up to valid_before
+fn parse_certificate(contents: &str) -> Result<Certificate, Box<dyn Error>> {
+    let mut parts = contents.split_whitespace();
+    let key_type = parts.next().ok_or("Empty certificate file")?;
+    let blob = STANDARD.decode(parts.next().ok_or("Missing certificate data")?)?;
+    let mut reader = Reader { data: &blob };
+
+    if reader.string()? != key_type.as_bytes() {
+        return Err("Certificate type does not match its data".into());
+    }
+    reader.string()?; // nonce
+
+    // Public key fields differ per key type but are all length-prefixed
+    let public_key_fields = match key_type {
+        "ssh-rsa-cert-v01@openssh.com" => 2,
+        "ssh-dss-cert-v01@openssh.com" => 4,
+        "ssh-ed25519-cert-v01@openssh.com" => 1,
+        "sk-ssh-ed25519-cert-v01@openssh.com" => 2,
+        "sk-ecdsa-sha2-nistp256-cert-v01@openssh.com" => 3,
+        t if t.starts_with("ecdsa-sha2-") && t.ends_with("-cert-v01@openssh.com") => 2,
+        _ => return Err(format!("Not an OpenSSH certificate: {}", key_type).into()),
+    };
+    for _ in 0..public_key_fields {
+        reader.string()?;
+    }
+
+    reader.u64()?; // serial
+    reader.u32()?; // certificate type
+    let key_id = String::from_utf8_lossy(reader.string()?).into_owned();
+    reader.string()?; // valid principals
+    reader.u64()?; // valid after
+    let valid_before = reader.u64()?;
+
+    Ok(Certificate {
+        key_id,
+        valid_before,
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.data.len() < len {
+            return Err("Truncated certificate".into());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Signed with `ssh-keygen -s ca -I deploy-<type> -n deploy
+    // -V 20240101000000Z:20300101000000Z`; the sk public keys were assembled by hand
+    // since generating them needs a security key
+    const RSA: &str = include_str!("testdata/rsa-cert.pub");
+    const ED25519: &str = include_str!("testdata/ed25519-cert.pub");
+    const ECDSA: &str = include_str!("testdata/ecdsa-cert.pub");
+    const SK_ED25519: &str = include_str!("testdata/sk-ed25519-cert.pub");
+    const SK_ECDSA: &str = include_str!("testdata/sk-ecdsa-cert.pub");
+    // Signed with `-I "" -V always:forever`
+    const FOREVER: &str = include_str!("testdata/forever-cert.pub");
+    const PUBLIC_KEY: &str = include_str!("testdata/ed25519.pub");
+
+    // 2030-01-01T00:00:00Z
+    const VALID_BEFORE: u64 = 1_893_456_000;
+
+    fn error(contents: &str) -> String {
+        match parse_certificate(contents) {
+            Ok(_) => panic!("parsed {:?}", contents),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    // Certificate with its data cut to `len` bytes
+    fn truncated(contents: &str, len: usize) -> String {
+        let mut parts = contents.split_whitespace();
+        let key_type = parts.next().unwrap();
+        let blob = STANDARD.decode(parts.next().unwrap()).unwrap();
+        format!("{} {}", key_type, STANDARD.encode(&blob[..len]))
+    }
+
+    #[test]
+    fn parses_every_key_type() {
+        for (contents, key_id) in [
+            (RSA, "deploy-rsa"),
+            (ED25519, "deploy-ed25519"),
+            (ECDSA, "deploy-ecdsa"),
+            (SK_ED25519, "deploy-sk-ed25519"),
+            (SK_ECDSA, "deploy-sk-ecdsa"),
+        ] {
+            let certificate = parse_certificate(contents).unwrap();
+            assert_eq!(certificate.key_id, key_id);
+            assert_eq!(certificate.valid_before, VALID_BEFORE);
+        }
+    }
+
+    #[test]
+    fn parses_certificate_valid_forever() {
+        let certificate = parse_certificate(FOREVER).unwrap();
+        assert_eq!(certificate.key_id, "");
+        assert_eq!(certificate.valid_before, u64::MAX);
+    }
+
+    #[test]
+    fn rejects_truncated_certificates() {
+        // The ed25519 fields up to valid_before take 168 bytes
+        for len in 1..168 {
+            assert_eq!(error(&truncated(ED25519, len)), "Truncated certificate");
+        }
+        assert!(parse_certificate(&truncated(ED25519, 168)).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert_eq!(error(""), "Empty certificate file");
+        assert_eq!(
+            error("ssh-ed25519-cert-v01@openssh.com"),
+            "Missing certificate data"
+        );
+        error("ssh-ed25519-cert-v01@openssh.com not-base64!");
+        assert_eq!(
+            error(&ED25519.replacen("ssh-ed25519", "ssh-rsa", 1)),
+            "Certificate type does not match its data"
+        );
+        assert_eq!(error(PUBLIC_KEY), "Not an OpenSSH certificate: ssh-ed25519");
+    }
+}
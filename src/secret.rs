@@ -0,0 +1,51 @@
+use std::env;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Secrets are encrypted with the `age` CLI: to AGE_RECIPIENT (a public key or
+// recipients file) when stored, with AGE_IDENTITY_FILE when revealed
+pub fn encrypt(plaintext: &str) -> Result<String, Box<dyn Error>> {
+    let recipient =
+        env::var("AGE_RECIPIENT").map_err(|_| "AGE_RECIPIENT environment variable not set")?;
+    let mut age = Command::new("age");
+    age.arg("--armor");
+    if std::path::Path::new(&recipient).is_file() {
+        age.args(["--recipients-file", &recipient]);
+    } else {
+        age.args(["--recipient", &recipient]);
+    }
+    run_age(&mut age, plaintext)
+}
+
+pub fn decrypt(ciphertext: &str) -> Result<String, Box<dyn Error>> {
+    let identity = env::var("AGE_IDENTITY_FILE")
+        .map_err(|_| "AGE_IDENTITY_FILE environment variable not set")?;
+    let mut age = Command::new("age");
+    age.args(["--decrypt", "--identity", &identity]);
+    run_age(&mut age, ciphertext)
+}
+
+fn run_age(age: &mut Command, input: &str) -> Result<String, Box<dyn Error>> {
+    let mut child = age
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run age: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("Failed to open age stdin")?
+        .write_all(input.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "age failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
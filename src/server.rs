@@ -0,0 +1,296 @@
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::io::Read;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::api_keys::{self, Access, Scope};
+use crate::hooks::Hooks;
+
+#[derive(Deserialize)]
+struct IngestToken {
+    tenant: Option<String>,
+    name: String,
+    expires_at: String,
+}
+
+impl IngestToken {
+    fn tenant(&self) -> &str {
+        self.tenant.as_deref().unwrap_or(crate::DEFAULT_TENANT)
+    }
+}
+
+// POST /ingest accepts a single token or an array of tokens
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IngestPayload {
+    One(IngestToken),
+    Many(Vec<IngestToken>),
+}
+
+// Starts the HTTP server on a background thread with its own database connection
+pub fn spawn(
+    listen_addr: &str,
+    db_path: &str,
+    ingest_token: Option<String>,
+    read_only: bool,
+    hooks: Hooks,
+) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(listen_addr)?;
+    let conn = crate::init_db(db_path)?;
+    println!(
+        "Listening on http://{}{}",
+        listen_addr,
+        if read_only { " (read-only)" } else { "" }
+    );
+
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let (status, body) = handle(
+                &conn,
+                ingest_token.as_deref(),
+                read_only,
+                &hooks,
+                &mut request,
+            );
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .expect("static header is valid"),
+                );
+            if let Err(e) = request.respond(response) {
+                eprintln!("Failed to send HTTP response: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle(
+    conn: &Connection,
+    ingest_token: Option<&str>,
+    read_only: bool,
+    hooks: &Hooks,
+    request: &mut Request,
+) -> (u16, Value) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let required = match (request.method(), path) {
+        (Method::Get, "/tokens") => Scope::Read,
+        (Method::Post, "/ingest") => Scope::Write,
+        (Method::Get, "/api-keys") => Scope::Admin,
+        (_, "/tokens" | "/ingest" | "/api-keys") => {
+            return (405, json!({ "error": "Method not allowed" }));
+        }
+        _ => return (404, json!({ "error": "Not found" })),
+    };
+    let access = match request_access(conn, request, ingest_token) {
+        Ok(Some(access)) => access,
+        Ok(None) => return (401, json!({ "error": "Unauthorized" })),
+        Err(e) => {
+            eprintln!("Failed to check API key: {}", e);
+            return (500, json!({ "error": "Storage error" }));
+        }
+    };
+    if access.scope < required {
+        return (403, json!({ "error": "API key lacks the required scope" }));
+    }
+    let allowed = access.tenant.as_deref();
+
+    match path {
+        "/tokens" => list(conn, query, allowed),
+        // Other tenants' keys are listed too
+        "/api-keys" if allowed.is_some() => {
+            (403, json!({ "error": "API key is limited to a tenant" }))
+        }
+        "/api-keys" => match api_keys::list(conn) {
+            Ok(keys) => (200, json!(keys)),
+            Err(e) => {
+                eprintln!("Failed to list API keys: {}", e);
+                (500, json!({ "error": "Storage error" }))
+            }
+        },
+        _ if read_only => (403, json!({ "error": "API is read-only" })),
+        _ => handle_ingest(conn, hooks, request, allowed),
+    }
+}
+
+// GET /tokens lists all tokens, or those of the tenant given as ?tenant=. Keys limited to
+// a tenant only see its tokens.
+fn list(conn: &Connection, query: &str, allowed: Option<&str>) -> (u16, Value) {
+    let tenant = match query
+        .split('&')
+        .find_map(|param| param.strip_prefix("tenant="))
+        .map(percent_decode)
+    {
+        Some(None) => return (400, json!({ "error": "Invalid tenant" })),
+        Some(Some(tenant)) => Some(tenant),
+        None => None,
+    };
+    let tenant = match (tenant, allowed) {
+        (Some(tenant), Some(allowed)) if tenant != allowed => return forbidden_tenant(allowed),
+        (tenant, allowed) => tenant.or(allowed.map(str::to_string)),
+    };
+    match crate::get_all_tokens(conn, tenant.as_deref()) {
+        Ok(tokens) => (200, json!(tokens)),
+        Err(e) => {
+            eprintln!("Failed to list tokens: {}", e);
+            (500, json!({ "error": "Storage error" }))
+        }
+    }
+}
+
+// Keys limited to a tenant ingest into it, and may not name another one
+fn handle_ingest(
+    conn: &Connection,
+    hooks: &Hooks,
+    request: &mut Request,
+    allowed: Option<&str>,
+) -> (u16, Value) {
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+        return (400, json!({ "error": e.to_string() }));
+    }
+    let mut tokens = match parse_payload(&body) {
+        Ok(tokens) => tokens,
+        Err(e) => return (400, json!({ "error": e.to_string() })),
+    };
+    if let Some(allowed) = allowed {
+        for token in &mut tokens {
+            match &token.tenant {
+                Some(tenant) if tenant != allowed => return forbidden_tenant(allowed),
+                Some(_) => {}
+                None => token.tenant = Some(allowed.to_string()),
+            }
+        }
+    }
+
+    match ingest(conn, &tokens) {
+        Ok(renewals) => {
+            for (token, previous) in renewals {
+                hooks.renewed(token.tenant(), &token.name, &previous, &token.expires_at);
+            }
+            (200, json!({ "ingested": tokens.len() }))
+        }
+        Err(e) => {
+            eprintln!("Failed to ingest tokens: {}", e);
+            (500, json!({ "error": "Storage error" }))
+        }
+    }
+}
+
+fn parse_payload(body: &str) -> Result<Vec<IngestToken>, Box<dyn Error>> {
+    let tokens = match serde_json::from_str(body)? {
+        IngestPayload::One(token) => vec![token],
+        IngestPayload::Many(tokens) => tokens,
+    };
+    for token in &tokens {
+        NaiveDate::parse_from_str(&token.expires_at, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid expires_at for '{}': {}", token.name, e))?;
+    }
+    Ok(tokens)
+}
+
+// Creates new tokens and updates the expiry of known ones, keeping their state.
+// Returns the tokens whose expiry moved forward along with their previous expiry.
+fn ingest<'a>(
+    conn: &Connection,
+    tokens: &'a [IngestToken],
+) -> rusqlite::Result<Vec<(&'a IngestToken, String)>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut renewals = Vec::new();
+    for token in tokens {
+        if let Some(previous) =
+            crate::upsert_expiry(&tx, token.tenant(), &token.name, &token.expires_at)?
+        {
+            renewals.push((token, previous));
+        }
+    }
+    tx.commit()?;
+    Ok(renewals)
+}
+
+fn forbidden_tenant(allowed: &str) -> (u16, Value) {
+    (
+        403,
+        json!({ "error": format!("API key is limited to tenant '{}'", allowed) }),
+    )
+}
+
+// Query values are form-encoded: "+" is a space and %XX a byte of UTF-8
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = rest
+                    .get(..2)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+                bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                rest = &rest[2..];
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn request_access(
+    conn: &Connection,
+    request: &Request,
+    ingest_token: Option<&str>,
+) -> rusqlite::Result<Option<Access>> {
+    let Some(bearer) = request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Authorization"))
+        .find_map(|header| header.value.as_str().strip_prefix("Bearer "))
+    else {
+        return Ok(None);
+    };
+    if ingest_token.is_some_and(|token| constant_time_eq(bearer.as_bytes(), token.as_bytes())) {
+        return Ok(Some(Access {
+            scope: Scope::Admin,
+            tenant: None,
+        }));
+    }
+    api_keys::authenticate(conn, bearer)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percent_decode;
+
+    #[test]
+    fn decodes_query_values() {
+        assert_eq!(percent_decode("payments").as_deref(), Some("payments"));
+        assert_eq!(
+            percent_decode("platform%2Dteam").as_deref(),
+            Some("platform-team")
+        );
+        assert_eq!(percent_decode("a+b").as_deref(), Some("a b"));
+        assert_eq!(percent_decode("a%2bb").as_deref(), Some("a+b"));
+        assert_eq!(percent_decode("caf%C3%A9").as_deref(), Some("café"));
+    }
+
+    #[test]
+    fn rejects_malformed_escapes() {
+        assert_eq!(percent_decode("100%"), None);
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%+1"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%FF"), None);
+    }
+}
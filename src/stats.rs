@@ -0,0 +1,218 @@
+use chrono::{Duration, Local, NaiveDate};
+use clap::{Subcommand, ValueEnum};
+use rusqlite::{Connection, params};
+use std::error::Error;
+
+use crate::Kind;
+
+#[derive(Subcommand)]
+pub enum Report {
+    /// How far ahead of expiry tokens are renewed, late renewals and overdue tokens
+    Rotations,
+    /// Items that expire soon (or already have), with their vendor and renewal cost
+    Renewals {
+        /// How far ahead to look, in days (90 or 90d) or weeks (12w)
+        #[arg(long, default_value = "90d", value_parser = parse_days)]
+        within: i64,
+        /// Total the cost, overall and per vendor
+        #[arg(long)]
+        sum_cost: bool,
+    },
+}
+
+pub fn parse_days(value: &str) -> Result<i64, String> {
+    let (number, factor) = match value.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (value.strip_suffix('d').unwrap_or(value), 1),
+    };
+    number
+        .parse::<i64>()
+        .map(|number| number * factor)
+        .map_err(|_| format!("'{}' is not a number of days (90d) or weeks (12w)", value))
+}
+
+// Renewal recorded in the history. The lead time is the number of days between the
+// renewal and the expiry it replaced, negative when the token had already expired.
+struct Renewal {
+    name: String,
+    kind: Kind,
+    old_expires_at: String,
+    renewed_on: NaiveDate,
+    lead_days: i64,
+}
+
+pub fn run(conn: &Connection, tenant: &str, report: Report) -> Result<(), Box<dyn Error>> {
+    match report {
+        Report::Rotations => rotations(conn, tenant),
+        Report::Renewals { within, sum_cost } => upcoming_renewals(conn, tenant, within, sum_cost),
+    }
+}
+
+fn upcoming_renewals(
+    conn: &Connection,
+    tenant: &str,
+    within_days: i64,
+    sum_cost: bool,
+) -> Result<(), Box<dyn Error>> {
+    let until = (Local::now().date_naive() + Duration::days(within_days))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut stmt = conn.prepare(
+        "SELECT name, kind, vendor, expires_at, cost FROM tokens
+         WHERE tenant = ?1 AND expires_at <= ?2 AND deleted_at IS NULL
+         ORDER BY expires_at",
+    )?;
+    let rows = stmt
+        .query_map(params![tenant, until], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Kind>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<f64>>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    println!("Renewals due by {}:", until);
+    println!(
+        "{:<30} {:<14} {:<20} {:<12} {:>10}",
+        "Name", "Kind", "Vendor", "Expires", "Cost"
+    );
+    println!("{}", "-".repeat(90));
+    for (name, kind, vendor, expires_at, cost) in &rows {
+        println!(
+            "{:<30} {:<14} {:<20} {:<12} {:>10}",
+            name,
+            kind.as_str(),
+            vendor.as_deref().unwrap_or("-"),
+            expires_at,
+            cost.map_or_else(|| "-".to_string(), |cost| format!("{:.2}", cost))
+        );
+    }
+
+    if sum_cost {
+        // Vendors in order of first renewal
+        let mut vendors: Vec<(&str, f64)> = Vec::new();
+        for (_, _, vendor, _, cost) in &rows {
+            let vendor = vendor.as_deref().unwrap_or("(no vendor)");
+            let cost = cost.unwrap_or(0.0);
+            match vendors.iter_mut().find(|(name, _)| *name == vendor) {
+                Some((_, total)) => *total += cost,
+                None => vendors.push((vendor, cost)),
+            }
+        }
+
+        println!();
+        println!("Cost by vendor:");
+        for (vendor, total) in &vendors {
+            println!("  {:<28} {:>10.2}", vendor, total);
+        }
+        let total: f64 = vendors.iter().map(|(_, total)| total).sum();
+        let unpriced = rows.iter().filter(|row| row.4.is_none()).count();
+        println!("  {:<28} {:>10.2}", "Total", total);
+        if unpriced > 0 {
+            println!("  ({} item(s) without a cost are not included)", unpriced);
+        }
+    }
+
+    Ok(())
+}
+
+fn rotations(conn: &Connection, tenant: &str) -> Result<(), Box<dyn Error>> {
+    let renewals = renewals(conn, tenant)?;
+
+    println!("Renewals: {}", renewals.len());
+    for &kind in Kind::value_variants() {
+        let count = renewals
+            .iter()
+            .filter(|renewal| renewal.kind == kind)
+            .count();
+        if count > 0 {
+            println!("  {:<14} {}", kind.plural(), count);
+        }
+    }
+    if !renewals.is_empty() {
+        let mut lead_days: Vec<i64> = renewals.iter().map(|renewal| renewal.lead_days).collect();
+        lead_days.sort_unstable();
+        let average = lead_days.iter().sum::<i64>() as f64 / lead_days.len() as f64;
+        println!(
+            "Lead time: median {} days, average {:.1} days, shortest {} days",
+            lead_days[lead_days.len() / 2],
+            average,
+            lead_days[0]
+        );
+    }
+
+    println!();
+    println!("Renewed late:");
+    println!(
+        "{:<30} {:<12} {:<12} {}",
+        "Name", "Expired", "Renewed", "Days late"
+    );
+    println!("{}", "-".repeat(65));
+    for renewal in renewals.iter().filter(|renewal| renewal.lead_days < 0) {
+        println!(
+            "{:<30} {:<12} {:<12} {}",
+            renewal.name, renewal.old_expires_at, renewal.renewed_on, -renewal.lead_days
+        );
+    }
+
+    println!();
+    println!("Currently overdue:");
+    println!(
+        "{:<30} {:<14} {:<12} {}",
+        "Name", "Kind", "Expired", "Days overdue"
+    );
+    println!("{}", "-".repeat(75));
+    for token in crate::get_all_tokens(conn, Some(tenant))? {
+        let days_remaining = crate::days_until(&token.expires_at)?;
+        if days_remaining < 0 {
+            println!(
+                "{:<30} {:<14} {:<12} {}",
+                token.name,
+                token.kind.as_str(),
+                token.expires_at,
+                -days_remaining
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn renewals(conn: &Connection, tenant: &str) -> Result<Vec<Renewal>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT h.name, h.old_expires_at, h.changed_at, COALESCE(t.kind, 'token')
+         FROM history h
+         LEFT JOIN tokens t ON t.tenant = h.tenant AND t.name = h.name
+         WHERE h.tenant = ?1 AND h.new_expires_at > h.old_expires_at
+         ORDER BY h.changed_at",
+    )?;
+    let rows = stmt
+        .query_map(params![tenant], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Kind>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut renewals = Vec::new();
+    for (name, old_expires_at, changed_at, kind) in rows {
+        let expired_on = NaiveDate::parse_from_str(&old_expires_at, "%Y-%m-%d")?;
+        // changed_at is a "YYYY-MM-DD HH:MM:SS" timestamp
+        let renewed_on =
+            NaiveDate::parse_from_str(changed_at.get(..10).unwrap_or(&changed_at), "%Y-%m-%d")?;
+        renewals.push(Renewal {
+            name,
+            kind,
+            old_expires_at,
+            renewed_on,
+            lead_days: (expired_on - renewed_on).num_days(),
+        });
+    }
+    Ok(renewals)
+}
@@ -0,0 +1,142 @@
+use clap::Subcommand;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+
+use crate::{Config, Token};
+
+// Variables filled in by expand
+const VARIABLES: &[&str] = &["name", "kind", "expires_at", "days"];
+
+#[derive(Subcommand)]
+pub enum Template {
+    /// Check MESSAGE_TEMPLATE_<DAYS> and token messages for unknown variables
+    Check,
+    /// Print the alert a token would get
+    Preview {
+        #[arg(long)]
+        token: String,
+        /// Days remaining to render the alert for, instead of the token's own
+        #[arg(long, allow_hyphen_values = true)]
+        days: Option<i64>,
+    },
+}
+
+pub fn run(
+    conn: &Connection,
+    config: &Config,
+    tenant: &str,
+    command: Template,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Template::Check => {
+            let mut problems = config.milestones.problems();
+            for token in crate::get_all_tokens(conn, Some(tenant))? {
+                if let Some(message) = &token.message {
+                    problems.extend(
+                        lint(message)
+                            .into_iter()
+                            .map(|problem| format!("Message of '{}': {}", token.name, problem)),
+                    );
+                }
+            }
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                return Err(crate::exit::invalid(format!(
+                    "{} template problem(s) found",
+                    problems.len()
+                )));
+            }
+            eprintln!("All templates are valid");
+        }
+        Template::Preview { token, days } => {
+            let token = crate::get_all_tokens(conn, Some(tenant))?
+                .into_iter()
+                .find(|candidate| candidate.name == token)
+                .ok_or_else(|| crate::exit::not_found(format!("Token '{}' not found", token)))?;
+            let days = match days {
+                Some(days) => days,
+                None => crate::days_until(&token.expires_at)?,
+            };
+            println!("{}", crate::alert_text(config, &token, days));
+        }
+    }
+    Ok(())
+}
+
+// Alert text per countdown milestone, from MESSAGE_TEMPLATE_<DAYS> variables. An alert
+// uses the nearest milestone at or above its days remaining: with 30, 3 and 0 set, an
+// item 10 days out gets the 30-day text and an expired one the 0-day text. Items further
+// out than every milestone keep the built-in text.
+#[derive(Debug, Default)]
+pub struct Milestones(BTreeMap<i64, String>);
+
+impl Milestones {
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let mut templates = BTreeMap::new();
+        for (key, value) in env::vars() {
+            let Some(days) = key.strip_prefix("MESSAGE_TEMPLATE_") else {
+                continue;
+            };
+            let days: u32 = days
+                .parse()
+                .map_err(|_| format!("{} must end in a number of days", key))?;
+            templates.insert(i64::from(days), value);
+        }
+        Ok(Self(templates))
+    }
+
+    // Lint findings of every milestone, prefixed with its variable name
+    pub fn problems(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .flat_map(|(days, template)| {
+                lint(template)
+                    .into_iter()
+                    .map(move |problem| format!("MESSAGE_TEMPLATE_{}: {}", days, problem))
+            })
+            .collect()
+    }
+
+    pub fn for_days(&self, days_remaining: i64) -> Option<&str> {
+        self.0
+            .range(days_remaining.max(0)..)
+            .next()
+            .map(|(_, template)| template.as_str())
+    }
+}
+
+// Fills in {name}, {kind}, {expires_at} and {days} (negative once expired)
+pub fn expand(text: &str, token: &Token, days_remaining: i64) -> String {
+    text.replace("{name}", &token.name)
+        .replace("{kind}", token.kind.label())
+        .replace("{expires_at}", &token.expires_at)
+        .replace("{days}", &days_remaining.to_string())
+}
+
+// Unknown {variables} and unclosed braces, which expand would leave in the alert as is
+fn lint(text: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        match rest.find(['{', '}']) {
+            Some(end) if rest[end..].starts_with('}') => {
+                let variable = &rest[..end];
+                if !VARIABLES.contains(&variable) {
+                    problems.push(format!(
+                        "unknown variable {{{}}}, use one of {{{}}}",
+                        variable,
+                        VARIABLES.join("}, {")
+                    ));
+                }
+                rest = &rest[end + 1..];
+            }
+            _ => problems.push("'{' is never closed".to_string()),
+        }
+    }
+    problems
+}
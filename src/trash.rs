@@ -0,0 +1,105 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use clap::Subcommand;
+use rusqlite::{Connection, params};
+use std::error::Error;
+
+use crate::Kind;
+
+#[derive(Subcommand)]
+pub enum Trash {
+    /// List removed items and when they are deleted for good
+    List,
+    /// Track removed items again
+    Restore {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+}
+
+pub fn run(
+    conn: &Connection,
+    tenant: &str,
+    command: Trash,
+    retention_days: i64,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Trash::List => list(conn, tenant, retention_days),
+        Trash::Restore { names } => {
+            // All or nothing, so a typo in one name leaves the others in the trash
+            let tx = conn.unchecked_transaction()?;
+            for name in &names {
+                let restored = tx.execute(
+                    "UPDATE tokens SET deleted_at = NULL
+                     WHERE tenant = ?1 AND name = ?2 AND deleted_at IS NOT NULL",
+                    params![tenant, name],
+                )?;
+                if restored == 0 {
+                    return Err(crate::exit::not_found(format!(
+                        "'{}' is not in the trash",
+                        name
+                    )));
+                }
+            }
+            tx.commit()?;
+            for name in &names {
+                eprintln!("Token '{}' restored", name);
+            }
+            Ok(())
+        }
+    }
+}
+
+// Deletes tokens trashed more than `retention_days` ago for good, returning how many.
+// A retention of 0 keeps them.
+pub fn purge(conn: &Connection, retention_days: i64) -> rusqlite::Result<usize> {
+    if retention_days <= 0 {
+        return Ok(0);
+    }
+    let cutoff = (Utc::now() - Duration::days(retention_days))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    conn.execute("DELETE FROM tokens WHERE deleted_at < ?1", params![cutoff])
+}
+
+fn list(conn: &Connection, tenant: &str, retention_days: i64) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, kind, expires_at, deleted_at FROM tokens
+         WHERE tenant = ?1 AND deleted_at IS NOT NULL
+         ORDER BY deleted_at",
+    )?;
+    let items = stmt
+        .query_map(params![tenant], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Kind>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    println!(
+        "{:<30} {:<14} {:<12} {:<20} {}",
+        "Name", "Kind", "Expires", "Removed", "Deleted On"
+    );
+    println!("{}", "-".repeat(92));
+    for (name, kind, expires_at, deleted_at) in items {
+        let purged_on = if retention_days > 0 {
+            let removed = NaiveDateTime::parse_from_str(&deleted_at, "%Y-%m-%d %H:%M:%S")?;
+            (removed + Duration::days(retention_days))
+                .format("%Y-%m-%d")
+                .to_string()
+        } else {
+            "Never".to_string()
+        };
+        println!(
+            "{:<30} {:<14} {:<12} {:<20} {}",
+            name,
+            kind.as_str(),
+            expires_at,
+            deleted_at,
+            purged_on
+        );
+    }
+    Ok(())
+}